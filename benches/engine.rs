@@ -0,0 +1,63 @@
+//! Benchmarks of the engine's hot paths, with seeded randomness so that every run does
+//! the same work. Run with `cargo bench --no-default-features` to skip the GUI.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use itertools::Itertools;
+use rand::{rngs::StdRng, SeedableRng};
+
+use mancala_bot::{
+    benchmark::mid_game_state,
+    game_state::GameState,
+    mcts::{compute_rollout, MCTSContext, PlayedMoves},
+};
+
+const SEED: u64 = 0;
+
+/// The number of MCTS samples per iteration of the `ponder_samples` benchmark.
+const PONDER_SAMPLES: usize = 1000;
+
+fn rollout(c: &mut Criterion) {
+    let game_state = GameState::default();
+    let mut rng = StdRng::seed_from_u64(SEED);
+    c.bench_function("compute_rollout", |b| {
+        b.iter(|| {
+            compute_rollout(black_box(game_state.clone()), &mut PlayedMoves::default(), &mut rng)
+        });
+    });
+}
+
+fn make_move(c: &mut Criterion) {
+    // make every valid move from a position with a mix of short moves, laps, extra
+    // turns, and captures
+    let game_state = mid_game_state();
+    let valid_moves = game_state.valid_moves().collect_vec();
+    c.bench_function("make_move", |b| {
+        b.iter(|| {
+            for &hole in &valid_moves {
+                let mut next_state = black_box(&game_state).clone();
+                next_state.make_move(black_box(hole));
+                black_box(next_state);
+            }
+        });
+    });
+}
+
+fn valid_moves(c: &mut Criterion) {
+    let game_state = mid_game_state();
+    c.bench_function("valid_moves", |b| {
+        b.iter(|| black_box(&game_state).valid_moves().count());
+    });
+}
+
+fn ponder_samples(c: &mut Criterion) {
+    let game_state = GameState::default();
+    c.bench_function("ponder_samples", |b| {
+        b.iter(|| {
+            let mut mcts_context = MCTSContext::with_seed(2_000_000, SEED);
+            mcts_context.ponder_samples(black_box(&game_state), PONDER_SAMPLES)
+        });
+    });
+}
+
+criterion_group!(benches, rollout, make_move, valid_moves, ponder_samples);
+criterion_main!(benches);
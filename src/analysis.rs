@@ -0,0 +1,264 @@
+//! One-shot, headless analysis of a position. This is the primary programmatic
+//! interface to the bot: `analyze` runs a fresh search on a game state for a given
+//! budget and returns everything it found, with no GUI or worker thread involved.
+
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+use itertools::Itertools;
+use num_format::{Locale, ToFormattedString};
+
+use crate::{
+    game_state::{GameState, Player, SWAP_MOVE},
+    mcts::{MCTSContext, OptionStats, DEFAULT_CACHE_SIZE_LIMIT},
+};
+
+/// How long the search ponders between checks of its budget.
+const PONDER_CHUNK: Duration = Duration::from_millis(10);
+
+/// The maximum length of the principal line in an `Analysis`.
+const MAX_LINE_PLIES: usize = 20;
+
+/// How much searching to do for an analysis.
+#[derive(Debug, Clone, Copy)]
+pub enum Budget {
+    /// Search for (at least) the given amount of time.
+    Time(Duration),
+
+    /// Search until (at least) the given number of samples have been performed.
+    Samples(usize),
+}
+
+/// The analysis of one of the valid moves in a position.
+#[derive(Debug, Clone, Copy)]
+pub struct MoveAnalysis {
+    /// The move (a hole index, or `SWAP_MOVE`).
+    pub hole: usize,
+
+    /// The search statistics for the move, from the perspective of the player making it.
+    pub stats: OptionStats,
+
+    /// The estimated probability that the move leads to a win (see
+    /// `OptionStats::win_probability`).
+    pub win_probability: f32,
+}
+
+/// The results of analyzing a position with `analyze`.
+#[derive(Debug, Clone)]
+pub struct Analysis {
+    /// The best move, or None if the game is over.
+    pub best_move: Option<usize>,
+
+    /// The expected final score (for the player to move) after the best move, or the
+    /// actual final score if the game is over.
+    pub expected_score: f32,
+
+    /// The estimated probability that the player to move wins.
+    pub win_probability: f32,
+
+    /// The most-visited line of play from the position, for both players.
+    pub principal_line: Vec<usize>,
+
+    /// The analysis of each valid move, in the order of `GameState::valid_moves`.
+    pub moves: Vec<MoveAnalysis>,
+
+    /// The number of samples performed by the search.
+    pub num_samples: usize,
+}
+
+/// Searches the given state with a fresh `MCTSContext` (with its default settings)
+/// until the budget is used up, and returns the results.
+#[must_use]
+pub fn analyze(game_state: &GameState, budget: Budget) -> Analysis {
+    if let Some(score) = game_state.result() {
+        let score = match game_state.cur_player {
+            Player::Player1 => f32::from(score),
+            Player::Player2 => -f32::from(score),
+        };
+        return Analysis {
+            best_move: None,
+            expected_score: score,
+            win_probability: outcome_probability(score),
+            principal_line: Vec::new(),
+            moves: Vec::new(),
+            num_samples: 0,
+        };
+    }
+
+    let mut context = MCTSContext::new(DEFAULT_CACHE_SIZE_LIMIT);
+    let start_time = Instant::now();
+    let mut num_samples = 0;
+    loop {
+        num_samples += context.ponder(game_state, PONDER_CHUNK).num_samples;
+        let budget_reached = match budget {
+            Budget::Time(duration) => start_time.elapsed() >= duration,
+            Budget::Samples(samples) => num_samples >= samples,
+        };
+        if budget_reached {
+            break;
+        }
+    }
+
+    let stats = context
+        .stats_or_forced(game_state)
+        .expect("a searched state should have stats");
+    let moves = game_state
+        .valid_moves()
+        .zip_eq(&stats.options)
+        .map(|(hole, &stats)| MoveAnalysis {
+            hole,
+            stats,
+            win_probability: stats.win_probability(),
+        })
+        .collect_vec();
+    let best = moves[stats.best_option()];
+    let principal_line = context
+        .principal_line(game_state, MAX_LINE_PLIES)
+        .into_iter()
+        .map(|step| step.hole)
+        .collect();
+
+    Analysis {
+        best_move: Some(best.hole),
+        expected_score: *best.stats.expected_score(),
+        win_probability: best.win_probability,
+        principal_line,
+        moves,
+        num_samples,
+    }
+}
+
+/// Returns 1 for a positive score, 0 for a negative score, and 0.5 for a draw.
+#[must_use]
+fn outcome_probability(score: f32) -> f32 {
+    if score > 0.0 {
+        1.0
+    } else if score < 0.0 {
+        0.0
+    } else {
+        0.5
+    }
+}
+
+/// Explains in a few words why a move might be chosen: its immediate effects (a swap,
+/// an extra turn, a capture, ending the game), or failing those, that the search rates
+/// it best. If `stats` is given, the move's expected score is appended.
+#[must_use]
+pub fn explain_move(game_state: &GameState, hole: usize, stats: Option<&OptionStats>) -> String {
+    let mut reasons = Vec::new();
+    if hole == SWAP_MOVE {
+        reasons.push("takes over the opponent's position".to_string());
+    } else {
+        let effects = game_state.move_effects(hole);
+        if effects.extra_turn {
+            reasons.push("lands in the store for an extra turn".to_string());
+        }
+        if effects.captured > 0 {
+            reasons.push(format!("captures {} stones", effects.captured));
+        }
+        if effects.ends_game {
+            reasons.push("ends the game".to_string());
+        }
+    }
+    if reasons.is_empty() {
+        reasons.push("is the highest-rated continuation".to_string());
+    }
+
+    let mut explanation = format!("This move {}", reasons.join(", "));
+    if let Some(stats) = stats {
+        explanation += &format!(" (expected score {:+.1})", stats.expected_score());
+    }
+    explanation
+}
+
+/// Returns a short description of a move.
+fn describe_move(hole: usize) -> String {
+    if hole == SWAP_MOVE {
+        "swap".to_string()
+    } else {
+        format!("hole {}", hole + 1)
+    }
+}
+
+impl fmt::Display for Analysis {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.best_move {
+            Some(hole) => writeln!(
+                f,
+                "best move: {} ({:+.2}, {:.1}% win)",
+                describe_move(hole),
+                self.expected_score,
+                self.win_probability * 100.0,
+            )?,
+            None => writeln!(f, "game over: {:+}", self.expected_score)?,
+        }
+        writeln!(
+            f,
+            "principal line: {}",
+            self.principal_line
+                .iter()
+                .map(|&hole| describe_move(hole))
+                .join(", "),
+        )?;
+        for move_analysis in &self.moves {
+            let num_rollouts = move_analysis.stats.num_rollouts; // copy out of the packed struct
+            writeln!(
+                f,
+                "  {}: {} rollouts, {:+.2} ± {:.2}, {:.1}% win",
+                describe_move(move_analysis.hole),
+                num_rollouts.to_formatted_string(&Locale::en),
+                move_analysis.stats.expected_score(),
+                move_analysis.stats.score_std_error(),
+                move_analysis.win_probability * 100.0,
+            )?;
+        }
+        write!(f, "{} samples", self.num_samples.to_formatted_string(&Locale::en))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state::GameStateBuilder;
+
+    #[test]
+    fn explains_extra_turns() {
+        let game_state = GameState::default();
+        assert_eq!(
+            explain_move(&game_state, 3, None),
+            "This move lands in the store for an extra turn",
+        );
+    }
+
+    #[test]
+    fn explains_captures() {
+        let game_state = GameStateBuilder::new().p1_holes([1, 0, 0, 0, 1, 0]).build();
+        assert_eq!(explain_move(&game_state, 4, None), "This move captures 4 stones");
+
+        // taking the opponent's last stones also ends the game
+        let game_state = GameStateBuilder::new()
+            .p1_holes([0, 0, 0, 0, 1, 1])
+            .p2_holes([0, 0, 3, 0, 0, 0])
+            .build();
+        assert_eq!(
+            explain_move(&game_state, 4, None),
+            "This move captures 3 stones, ends the game",
+        );
+    }
+
+    #[test]
+    fn explains_plain_moves_by_their_score() {
+        let game_state = GameState::default();
+        let stats = OptionStats {
+            num_rollouts: 4,
+            total_score: 10.0,
+            ..OptionStats::default()
+        };
+        assert_eq!(
+            explain_move(&game_state, 0, Some(&stats)),
+            "This move is the highest-rated continuation (expected score +2.5)",
+        );
+    }
+}
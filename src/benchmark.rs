@@ -0,0 +1,154 @@
+use std::time::{Duration, Instant};
+
+use itertools::Itertools;
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::{
+    game_state::{GameState, GameStateBuilder, Player},
+    mcts::{compute_rollout, compute_rollout_score, MCTSContext, PlayedMoves},
+};
+
+/// The seed for the randomness in `run_samples`, so that every run does the same work.
+const SEED: u64 = 0;
+
+/// The results of a benchmark run.
+#[derive(Debug, Clone, Copy)]
+pub struct BenchmarkResults {
+    /// Random rollouts from the start position per second.
+    pub rollouts_per_second: f64,
+
+    /// Moves made from a fixed mid-game position (see `mid_game_state`) per second.
+    pub moves_per_second: f64,
+
+    /// MCTS samples from the start position per second.
+    pub samples_per_second: f64,
+
+    /// The average search depth of the MCTS samples.
+    pub average_search_depth: f64,
+}
+
+impl BenchmarkResults {
+    /// Returns a single-line, machine-readable summary of the results.
+    #[must_use]
+    pub fn summary_line(&self) -> String {
+        format!(
+            "benchmark rollouts_per_sec={:.0} moves_per_sec={:.0} samples_per_sec={:.0} \
+             avg_depth={:.2}",
+            self.rollouts_per_second,
+            self.moves_per_second,
+            self.samples_per_second,
+            self.average_search_depth,
+        )
+    }
+}
+
+/// Returns the position that making moves is benchmarked on: a mid-game position with
+/// a mix of short moves, moves that lap the board, extra turns, and captures.
+#[must_use]
+pub fn mid_game_state() -> GameState {
+    GameStateBuilder::new()
+        .p1_holes([1, 0, 5, 2, 14, 3])
+        .p1_store(9)
+        .p2_holes([0, 6, 1, 0, 2, 3])
+        .p2_store(2)
+        .turn(Player::Player1)
+        .build()
+}
+
+/// Makes each valid move from `mid_game_state` (on a copy) in turn, `num_rounds`
+/// times, and returns the number of moves made.
+fn make_moves(num_rounds: u64) -> u64 {
+    let game_state = mid_game_state();
+    let valid_moves = game_state.valid_moves().collect_vec();
+    for _ in 0..num_rounds {
+        for &hole in &valid_moves {
+            let mut next_state = game_state.clone();
+            next_state.make_move(hole);
+        }
+    }
+    num_rounds * valid_moves.len() as u64
+}
+
+/// Benchmarks rollouts and MCTS on the start position, and making moves from a
+/// mid-game position, spending `duration` on each. Everything runs on the calling
+/// thread so that results are comparable across machines with different numbers of
+/// cores.
+#[must_use]
+pub fn run(duration: Duration, cache_size_limit: usize) -> BenchmarkResults {
+    let game_state = GameState::default();
+
+    // time plain rollouts
+    let start_time = Instant::now();
+    let mut num_rollouts = 0_u64;
+    while start_time.elapsed() < duration {
+        let _ = compute_rollout_score(game_state.clone());
+        num_rollouts += 1;
+    }
+    let rollouts_per_second = num_rollouts as f64 / start_time.elapsed().as_secs_f64();
+
+    // time making moves, checking the time after every batch
+    let start_time = Instant::now();
+    let mut num_moves = 0;
+    while start_time.elapsed() < duration {
+        num_moves += make_moves(1000);
+    }
+    let moves_per_second = num_moves as f64 / start_time.elapsed().as_secs_f64();
+
+    // time full MCTS samples
+    let mut mcts_context = MCTSContext::new(cache_size_limit);
+    let start_time = Instant::now();
+    let ponder_stats = mcts_context.ponder(&game_state, duration);
+    let samples_per_second = ponder_stats.num_samples as f64 / start_time.elapsed().as_secs_f64();
+    let average_search_depth = if ponder_stats.num_samples == 0 {
+        0.0
+    } else {
+        f64::from(ponder_stats.sum_depths) / ponder_stats.num_samples as f64
+    };
+
+    BenchmarkResults {
+        rollouts_per_second,
+        moves_per_second,
+        samples_per_second,
+        average_search_depth,
+    }
+}
+
+/// Like `run`, but does a fixed amount of work instead of running for a fixed time:
+/// `num_samples` rollouts, rounds of moves, and MCTS samples, with seeded randomness.
+/// Every run does exactly the same work, so differences in the rates only come from
+/// the code (and the machine).
+#[must_use]
+pub fn run_samples(num_samples: usize, cache_size_limit: usize) -> BenchmarkResults {
+    let game_state = GameState::default();
+
+    // time plain rollouts
+    let mut rng = StdRng::seed_from_u64(SEED);
+    let start_time = Instant::now();
+    for _ in 0..num_samples {
+        let _ = compute_rollout(game_state.clone(), &mut PlayedMoves::default(), &mut rng);
+    }
+    let rollouts_per_second = num_samples as f64 / start_time.elapsed().as_secs_f64();
+
+    // time making moves
+    let start_time = Instant::now();
+    let num_moves = make_moves(num_samples as u64);
+    let moves_per_second = num_moves as f64 / start_time.elapsed().as_secs_f64();
+
+    // time full MCTS samples
+    let mut mcts_context = MCTSContext::with_seed(cache_size_limit, SEED);
+    let start_time = Instant::now();
+    let ponder_stats = mcts_context.ponder_samples(&game_state, num_samples);
+    let samples_per_second = num_samples as f64 / start_time.elapsed().as_secs_f64();
+    let average_search_depth = if num_samples == 0 {
+        0.0
+    } else {
+        f64::from(ponder_stats.sum_depths) / num_samples as f64
+    };
+
+    BenchmarkResults {
+        rollouts_per_second,
+        moves_per_second,
+        samples_per_second,
+        average_search_depth,
+    }
+}
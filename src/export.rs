@@ -0,0 +1,44 @@
+use std::io::{self, Write};
+
+use itertools::Itertools;
+
+use crate::{
+    game_state::{GameState, SWAP_MOVE},
+    mcts::StateStats,
+};
+
+/// The default file that analysis is exported to.
+pub const DEFAULT_ANALYSIS_PATH: &str = "analysis.csv";
+
+/// Writes the per-move analysis for a state as CSV, with a header row and one row
+/// per valid move. Scores are from the perspective of the current player.
+pub fn write_analysis_csv(
+    mut writer: impl Write,
+    game_state: &GameState,
+    stats: &StateStats,
+) -> io::Result<()> {
+    writeln!(writer, "hole,visits,expected_score,win_probability,visit_share")?;
+    for (hole_index, option_stats) in game_state.valid_moves().zip_eq(&stats.options) {
+        let num_rollouts = option_stats.num_rollouts;
+        let visit_share = if stats.num_rollouts == 0 {
+            0.0
+        } else {
+            f64::from(num_rollouts) / f64::from(stats.num_rollouts)
+        };
+        let move_name = if hole_index == SWAP_MOVE {
+            "swap".to_string()
+        } else {
+            (hole_index + 1).to_string()
+        };
+        writeln!(
+            writer,
+            "{},{},{:.3},{:.4},{:.4}",
+            move_name,
+            num_rollouts,
+            option_stats.expected_score(),
+            option_stats.win_probability(),
+            visit_share,
+        )?;
+    }
+    writer.flush()
+}
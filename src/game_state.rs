@@ -1,8 +1,12 @@
-use std::{fmt, mem};
+use std::{fmt, iter, mem};
 
+use rand::Rng;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use static_assertions::const_assert;
 
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Player {
     Player1,
     Player2,
@@ -28,17 +32,240 @@ impl fmt::Display for Player {
     }
 }
 
+/// A score difference, (P1 score) - (P2 score), or a margin of victory.
+pub type Score = i16;
+
+/// The outcome of a finished game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameOutcome {
+    /// Player 1 won by the given (positive) margin.
+    Player1Wins(Score),
+
+    /// Player 2 won by the given (positive) margin.
+    Player2Wins(Score),
+
+    /// The game was drawn.
+    Draw,
+}
+
+impl GameOutcome {
+    /// Returns the outcome corresponding to a final score (P1 score) - (P2 score).
+    #[must_use]
+    pub fn from_score(score: Score) -> Self {
+        match score {
+            0 => GameOutcome::Draw,
+            score if score > 0 => GameOutcome::Player1Wins(score),
+            score => GameOutcome::Player2Wins(-score),
+        }
+    }
+
+    /// Returns the final score (P1 score) - (P2 score); the inverse of `from_score`.
+    #[must_use]
+    pub fn score(&self) -> Score {
+        match *self {
+            GameOutcome::Player1Wins(margin) => margin,
+            GameOutcome::Player2Wins(margin) => -margin,
+            GameOutcome::Draw => 0,
+        }
+    }
+
+    /// Returns the winning player, or None for a draw.
+    #[must_use]
+    pub fn winner(&self) -> Option<Player> {
+        match self {
+            GameOutcome::Player1Wins(_) => Some(Player::Player1),
+            GameOutcome::Player2Wins(_) => Some(Player::Player2),
+            GameOutcome::Draw => None,
+        }
+    }
+}
+
+impl fmt::Display for GameOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameOutcome::Player1Wins(margin) => write!(f, "{} wins by {margin}", Player::Player1),
+            GameOutcome::Player2Wins(margin) => write!(f, "{} wins by {margin}", Player::Player2),
+            GameOutcome::Draw => f.write_str("Draw"),
+        }
+    }
+}
+
 /// The number of holes on each player's side, not including their store.
 pub const HOLES_PER_SIDE: usize = 6;
 
-/// The number of initial stones in each hole.
+/// The number of initial stones in each hole, by default.
 pub const INITIAL_STONES_PER_HOLE: u8 = 4;
 
-// Assert that the total number of stones in the game will fit in an i8.
-const_assert!(HOLES_PER_SIDE * 2 * (INITIAL_STONES_PER_HOLE as usize) <= (i8::MAX as usize));
+/// The most initial stones in each hole that a game can start with (see
+/// `GameState::new_with`).
+pub const MAX_STONES_PER_HOLE: u8 = 6;
+
+const_assert!(INITIAL_STONES_PER_HOLE <= MAX_STONES_PER_HOLE);
+
+// Assert that the total number of stones in the game (and so any score) will fit in a Score.
+const_assert!(HOLES_PER_SIDE * 2 * (MAX_STONES_PER_HOLE as usize) <= (Score::MAX as usize));
+
+/// The most stones that a game can have in total, and so the most that any state may have.
+pub const TOTAL_STONES: usize = HOLES_PER_SIDE * 2 * (MAX_STONES_PER_HOLE as usize);
+
+/// The move that swaps sides under the pie rule (see `SwapOption`). Every other
+/// move is a hole index.
+pub const SWAP_MOVE: usize = HOLES_PER_SIDE;
+
+/// The maximum number of valid moves in any state (every hole, plus swapping).
+pub const MAX_MOVES: usize = HOLES_PER_SIDE + 1;
+
+/// The state of the pie rule: after Player 1's first turn, Player 2 may swap sides
+/// (taking over Player 1's position) instead of making a normal move.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SwapOption {
+    /// Swapping isn't possible (the rule is off, or the opportunity has passed).
+    Unavailable,
+
+    /// The rule is on and Player 1's first turn is in progress.
+    Pending,
+
+    /// Player 2 may play `SWAP_MOVE`.
+    Available,
+}
+
+/// Whether sowing puts stones into the opponent's store.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum StoreRule {
+    /// Stones are only sown into the current player's store (the standard rule).
+    #[default]
+    SkipOpponents,
+
+    /// Stones are sown into both stores. A last stone landing in the opponent's
+    /// store doesn't earn an extra turn.
+    SowIntoBoth,
+}
+
+/// When the last stone of a move captures stones.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum CaptureRule {
+    /// A last stone landing in an empty hole on the current player's side captures
+    /// the stones in the opposite hole, along with itself (if there are any).
+    #[default]
+    Standard,
+
+    /// There are no captures.
+    NoCaptures,
+
+    /// Like `Standard`, but the last stone is moved to the store even if the opposite
+    /// hole is empty.
+    EvenIfOppositeEmpty,
+}
+
+/// The family of rules that sowing and capturing follow.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Ruleset {
+    /// Stones are sown into the stores too, a last stone in the mover's store earns
+    /// an extra turn, and captures follow `CaptureRule` (the standard rules).
+    #[default]
+    Kalah,
+
+    /// Stones are only sown into holes, skipping the starting hole when going all the
+    /// way around, and there are no extra turns. A last stone that makes an opponent's
+    /// hole hold 2 or 3 captures it, along with each preceding opponent's hole that
+    /// also holds 2 or 3. `StoreRule` and `CaptureRule` don't apply. (The Oware rules
+    /// about feeding the opponent and grand slams aren't implemented.)
+    Oware,
+
+    /// Like `Kalah`, but when the last stone lands in a hole that already held stones,
+    /// they're all picked up and sown onward, until the last stone lands in an empty
+    /// hole or a store (or `MAX_RELAYS` pickups have been made).
+    Relay,
+}
+
+/// The most times a move can pick up stones to keep sowing under `Ruleset::Relay`,
+/// since relay sowing can go around the board forever in rare positions.
+const MAX_RELAYS: u32 = 100;
+
+/// What happens when the holes on one side are empty but the other side still has
+/// stones.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum EmptySideRule {
+    /// The game ends, and each player gets the stones left on their own side (the
+    /// standard rule).
+    #[default]
+    EndGame,
+
+    /// A player with no stones on their side passes, so the other player keeps moving
+    /// until a move puts stones on the empty side. The game ends once all the holes
+    /// are empty.
+    PassTurn,
+}
+
+/// The configurable rules of the game.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Rules {
+    pub store_rule: StoreRule,
+
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub capture_rule: CaptureRule,
+
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub empty_side_rule: EmptySideRule,
+
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub ruleset: Ruleset,
+}
+
+impl Rules {
+    /// The number of bits used by `to_bits`.
+    const NUM_BITS: u32 = 6;
+
+    /// Returns these rules packed into an integer with `NUM_BITS` bits.
+    #[must_use]
+    fn to_bits(self) -> u128 {
+        self.store_rule as u128
+            | ((self.capture_rule as u128) << 1)
+            | ((self.empty_side_rule as u128) << 3)
+            | ((self.ruleset as u128) << 4)
+    }
+
+    /// Unpacks rules packed by `to_bits`.
+    #[must_use]
+    fn from_bits(bits: u128) -> Self {
+        let store_rule = if bits & 1 == 0 {
+            StoreRule::SkipOpponents
+        } else {
+            StoreRule::SowIntoBoth
+        };
+        let capture_rule = match (bits >> 1) & 0b11 {
+            0 => CaptureRule::Standard,
+            1 => CaptureRule::NoCaptures,
+            _ => CaptureRule::EvenIfOppositeEmpty,
+        };
+        let empty_side_rule = if (bits >> 3) & 1 == 0 {
+            EmptySideRule::EndGame
+        } else {
+            EmptySideRule::PassTurn
+        };
+        let ruleset = match (bits >> 4) & 0b11 {
+            0 => Ruleset::Kalah,
+            1 => Ruleset::Oware,
+            _ => Ruleset::Relay,
+        };
+        Rules {
+            store_rule,
+            capture_rule,
+            empty_side_rule,
+            ruleset,
+        }
+    }
+}
 
 /// Represents a game state.
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct GameState {
     /// Which player's turn it currently is.
     pub cur_player: Player,
@@ -48,19 +275,76 @@ pub struct GameState {
 
     /// Player 2's state.
     pub p2_state: PlayerState,
+
+    /// Whether swapping sides is (or will become) a valid move.
+    pub swap_option: SwapOption,
+
+    /// The rules the game is played with.
+    pub rules: Rules,
 }
 
 impl Default for GameState {
     fn default() -> Self {
-        Self {
-            cur_player: Player::Player1,
-            p1_state: PlayerState::default(),
-            p2_state: PlayerState::default(),
-        }
+        GameState::new(Rules::default(), false)
     }
 }
 
 impl GameState {
+    /// Returns the initial game state for the given rules, optionally with the pie
+    /// rule (see `SwapOption`).
+    #[must_use]
+    pub fn new(rules: Rules, pie_rule: bool) -> Self {
+        Self::new_with(rules, pie_rule, INITIAL_STONES_PER_HOLE).unwrap()
+    }
+
+    /// Returns the initial game state for the given rules (optionally with the pie
+    /// rule), with `stones_per_hole` stones in each hole. Returns an error if it isn't
+    /// between 1 and `MAX_STONES_PER_HOLE`.
+    pub fn new_with(rules: Rules, pie_rule: bool, stones_per_hole: u8) -> Result<Self, String> {
+        if !(1..=MAX_STONES_PER_HOLE).contains(&stones_per_hole) {
+            return Err(format!(
+                "a game must start with between 1 and {MAX_STONES_PER_HOLE} stones per hole, \
+                 not {stones_per_hole}",
+            ));
+        }
+        let player_state = PlayerState::new([stones_per_hole; HOLES_PER_SIDE], 0);
+        Ok(Self {
+            cur_player: Player::Player1,
+            p1_state: player_state.clone(),
+            p2_state: player_state,
+            swap_option: if pie_rule {
+                SwapOption::Pending
+            } else {
+                SwapOption::Unavailable
+            },
+            rules,
+        })
+    }
+
+    /// Returns an initial game state (with the default rules) where `stones_per_side`
+    /// stones are scattered randomly among each player's holes. Both players get the
+    /// same arrangement, so neither is favored. Panics if `stones_per_side` is 0 (which
+    /// would be a game that's already over), or if this would be more than
+    /// `TOTAL_STONES` stones in total.
+    #[must_use]
+    pub fn random_start(rng: &mut impl Rng, stones_per_side: u8) -> Self {
+        assert!(stones_per_side > 0, "each side needs at least one stone");
+        assert!(
+            usize::from(stones_per_side) * 2 <= TOTAL_STONES,
+            "at most {TOTAL_STONES} stones are allowed",
+        );
+        let mut holes = [0; HOLES_PER_SIDE];
+        for _ in 0..stones_per_side {
+            holes[rng.gen_range(0..HOLES_PER_SIDE)] += 1;
+        }
+        let player_state = PlayerState::new(holes, 0);
+        Self {
+            p1_state: player_state.clone(),
+            p2_state: player_state,
+            ..Self::default()
+        }
+    }
+
     /// Returns a reference to the state for the given player.
     #[must_use]
     pub fn player(&self, player: Player) -> &PlayerState {
@@ -79,22 +363,83 @@ impl GameState {
         }
     }
 
-    /// Given the current player's hole selection, updates the game state.
-    /// Panics if `hole >= HOLES_PER_SIDE` or the selected hole is empty.
-    /// If debug assertions are enabled, panics if this state is a terminal state.
+    /// Given the current player's hole selection (or `SWAP_MOVE`), updates the game state.
+    /// Panics if `hole > HOLES_PER_SIDE`, the selected hole is empty, or swapping isn't
+    /// available. If debug assertions are enabled, panics if this state is a terminal state.
+    #[inline]
     pub fn make_move(&mut self, hole: usize) {
+        self.make_move_capturing(hole);
+    }
+
+    /// Like `make_move`, but returns what the sowing did (see `Sowing`).
+    fn make_move_capturing(&mut self, hole: usize) -> Sowing {
         debug_assert!(self.result().is_none()); // assert that this is not a terminal state
 
+        if hole == SWAP_MOVE {
+            // Player 2 takes over Player 1's position, and it becomes Player 1's turn
+            assert_eq!(self.swap_option, SwapOption::Available, "swapping is not available");
+            mem::swap(&mut self.p1_state, &mut self.p2_state);
+            self.cur_player = Player::Player1;
+            self.swap_option = SwapOption::Unavailable;
+            return Sowing {
+                captured: 0,
+                captured_holes: 0,
+                landing: None,
+            };
+        }
+
+        // sowing and capturing only move stones around, so the total is conserved
+        #[cfg(debug_assertions)]
+        let total_stones_before = self.total_stones();
+
+        let sowing = match self.rules.ruleset {
+            Ruleset::Kalah => self.sow_kalah(hole, false),
+            Ruleset::Oware => self.sow_oware(hole),
+            Ruleset::Relay => self.sow_kalah(hole, true),
+        };
+
+        // finally, toggle whose turn it is
+        self.cur_player = self.cur_player.other();
+
+        // under `EmptySideRule::PassTurn`, a player with an empty side passes back
+        // (under `EndGame`, the game is over instead; see `result`)
+        if self.rules.empty_side_rule == EmptySideRule::PassTurn
+            && self.player(self.cur_player).stones_in_holes() == 0
+        {
+            self.cur_player = self.cur_player.other();
+        }
+
+        // swapping becomes available when Player 1's first turn ends, and passes once
+        // Player 2 moves instead
+        self.swap_option = match self.swap_option {
+            SwapOption::Pending if self.cur_player == Player::Player2 => SwapOption::Available,
+            SwapOption::Available => SwapOption::Unavailable,
+            swap_option => swap_option,
+        };
+
+        debug_assert!(self.p1_state.is_cache_consistent() && self.p2_state.is_cache_consistent());
+        #[cfg(debug_assertions)]
+        debug_assert_eq!(self.total_stones(), total_stones_before, "stones were not conserved");
+        sowing
+    }
+
+    /// Sows the stones from the current player's given hole under `Ruleset::Kalah` (or
+    /// `Ruleset::Relay` if `relay` is set), handling captures and extra turns (by
+    /// flipping `cur_player` in advance), and returns what it did.
+    fn sow_kalah(&mut self, hole: usize, relay: bool) -> Sowing {
         let cur_player = self.cur_player;
+        let mut captured = 0;
+        let mut captured_holes = 0;
 
         // take the stones out of the selected hole
         assert!(hole < HOLES_PER_SIDE, "invalid hole index: {hole}");
-        let mut num_stones = mem::take(&mut self.player_mut(cur_player).holes[hole]) as usize;
+        let mut num_stones = self.player_mut(cur_player).take_stones(hole);
         assert!(num_stones > 0, "selected an empty hole");
 
         // repeatedly place stones in successive spots
         let mut player = self.cur_player;
         let mut hole = Some(hole);
+        let mut num_relays = 0;
 
         while num_stones > 0 {
             // advance to the next hole, and add a stone to it
@@ -102,13 +447,15 @@ impl GameState {
                 None => {
                     hole = Some(HOLES_PER_SIDE - 1);
                     player = player.other();
-                    self.player_mut(player).holes[HOLES_PER_SIDE - 1] += 1;
+                    self.player_mut(player).add_stone(HOLES_PER_SIDE - 1);
                 }
                 Some(0) => {
-                    if player != cur_player {
+                    let skip_store =
+                        player != cur_player && self.rules.store_rule == StoreRule::SkipOpponents;
+                    if skip_store {
                         hole = Some(HOLES_PER_SIDE - 1);
                         player = player.other();
-                        self.player_mut(player).holes[HOLES_PER_SIDE - 1] += 1;
+                        self.player_mut(player).add_stone(HOLES_PER_SIDE - 1);
                     } else {
                         hole = None;
                         self.player_mut(player).store += 1;
@@ -116,24 +463,45 @@ impl GameState {
                 }
                 Some(hole) => {
                     *hole -= 1;
-                    self.player_mut(player).holes[*hole] += 1;
+                    self.player_mut(player).add_stone(*hole);
                 }
             }
             num_stones -= 1;
+
+            // under relay sowing, a last stone that lands in an occupied hole picks up
+            // all of its stones to keep sowing
+            if relay && num_stones == 0 && num_relays < MAX_RELAYS {
+                if let Some(hole) = hole.filter(|&hole| self.player(player).holes[hole] > 1) {
+                    num_stones = self.player_mut(player).take_stones(hole);
+                    num_relays += 1;
+                }
+            }
         }
 
         // handle conditions based on where the last stone was placed
+        let landing = match hole {
+            Some(hole) => Landing::Hole(player, hole),
+            None => Landing::Store(player),
+        };
         if player == cur_player {
             if let Some(hole) = hole {
-                if self.player(cur_player).holes[hole] == 1 {
+                let capture_rule = self.rules.capture_rule;
+                if capture_rule != CaptureRule::NoCaptures
+                    && self.player(cur_player).holes[hole] == 1
+                {
                     // the last stone landed in an empty hole on the current player's side;
                     // capture any stones in the opposite hole
                     let other_hole_idx = (HOLES_PER_SIDE - 1) - hole;
-                    let captured_stones =
-                        mem::take(&mut self.player_mut(cur_player.other()).holes[other_hole_idx]);
+                    let captured_stones = self
+                        .player_mut(cur_player.other())
+                        .take_stones(other_hole_idx);
+                    captured = captured_stones;
                     if captured_stones > 0 {
+                        captured_holes = 1 << other_hole_idx;
+                    }
+                    if captured_stones > 0 || capture_rule == CaptureRule::EvenIfOppositeEmpty {
                         // additionally capture the 1 stone that landed in the empty hole
-                        self.player_mut(cur_player).holes[hole] = 0;
+                        self.player_mut(cur_player).take_stones(hole);
                         let captured_stones = captured_stones + 1;
 
                         self.player_mut(cur_player).store += captured_stones;
@@ -145,56 +513,704 @@ impl GameState {
                 self.cur_player = self.cur_player.other();
             }
         }
+        Sowing {
+            captured,
+            captured_holes,
+            landing: Some(landing),
+        }
+    }
 
-        // finally, toggle whose turn it is
-        self.cur_player = self.cur_player.other();
+    /// Sows the stones from the current player's given hole under `Ruleset::Oware`,
+    /// handling captures, and returns what it did.
+    fn sow_oware(&mut self, start_hole: usize) -> Sowing {
+        let cur_player = self.cur_player;
+
+        // take the stones out of the selected hole
+        assert!(start_hole < HOLES_PER_SIDE, "invalid hole index: {start_hole}");
+        let mut num_stones = self.player_mut(cur_player).take_stones(start_hole);
+        assert!(num_stones > 0, "selected an empty hole");
+
+        // place stones in successive holes around the board, skipping the stores and
+        // the starting hole
+        let mut player = cur_player;
+        let mut hole = start_hole;
+        while num_stones > 0 {
+            if hole == 0 {
+                hole = HOLES_PER_SIDE - 1;
+                player = player.other();
+            } else {
+                hole -= 1;
+            }
+            if player == cur_player && hole == start_hole {
+                continue;
+            }
+            self.player_mut(player).add_stone(hole);
+            num_stones -= 1;
+        }
+
+        // if the last stone made an opponent's hole 2 or 3, capture it, along with the
+        // preceding holes on their side for as long as they also hold 2 or 3
+        let landing = Landing::Hole(player, hole);
+        let mut captured = 0;
+        let mut captured_holes = 0;
+        if player != cur_player {
+            while hole < HOLES_PER_SIDE && matches!(self.player(player).holes[hole], 2 | 3) {
+                captured += self.player_mut(player).take_stones(hole);
+                captured_holes |= 1 << hole;
+                hole += 1;
+            }
+            self.player_mut(cur_player).store += captured;
+        }
+        Sowing {
+            captured,
+            captured_holes,
+            landing: Some(landing),
+        }
+    }
+
+    /// Returns what the given (legal) move does, beyond sowing: whether it earns an
+    /// extra turn, how many of the opponent's stones it captures, whether it ends the
+    /// game, and where its last stone lands.
+    #[must_use]
+    pub fn move_effects(&self, hole: usize) -> MoveEffects {
+        let mut next_state = self.clone();
+        let sowing = next_state.make_move_capturing(hole);
+        let ends_game = next_state.result().is_some();
+
+        // going again because the opponent's side is empty (under `EmptySideRule::PassTurn`)
+        // isn't an extra turn; only landing in the mover's own store is
+        let extra_turn = !ends_game && sowing.landing == Some(Landing::Store(self.cur_player));
+        MoveEffects {
+            extra_turn,
+            captured: sowing.captured,
+            captured_holes: sowing.captured_holes,
+            ends_game,
+            landing: sowing.landing,
+        }
+    }
+
+    /// Makes the given move if it's legal (see `is_legal_move`), or returns why it
+    /// isn't (leaving the state unchanged).
+    pub fn try_make_move(&mut self, hole: usize) -> Result<(), MoveError> {
+        let reason = if self.result().is_some() {
+            Some(MoveErrorReason::GameOver)
+        } else if hole == SWAP_MOVE {
+            (self.swap_option != SwapOption::Available).then_some(MoveErrorReason::SwapUnavailable)
+        } else if hole > HOLES_PER_SIDE {
+            Some(MoveErrorReason::OutOfRange)
+        } else if self.player(self.cur_player).holes[hole] == 0 {
+            Some(MoveErrorReason::EmptyHole)
+        } else {
+            None
+        };
+        match reason {
+            Some(reason) => Err(MoveError { hole, reason }),
+            None => {
+                self.make_move(hole);
+                Ok(())
+            }
+        }
+    }
+
+    /// Makes each of the given moves in turn, stopping at the first illegal one (see
+    /// `try_make_move`). The moves before it are still made.
+    pub fn apply_moves(&mut self, moves: &[usize]) -> Result<(), MoveError> {
+        moves.iter().try_for_each(|&hole| self.try_make_move(hole))
     }
 
     /// Returns the final game result Some((P1 score) - (P2 score)), or None
-    /// if the game is not yet over in this state.
+    /// if the game is not yet over in this state. Under `EmptySideRule::EndGame`, the
+    /// game is over once either side's holes are empty; under `PassTurn`, once the
+    /// player to move has no stones (since they'd otherwise have passed, the other
+    /// side is empty too, unless the state was constructed directly). Either way, each
+    /// player gets the stones left on their side.
     #[must_use]
-    pub fn result(&self) -> Option<i8> {
+    pub fn result(&self) -> Option<Score> {
         let p1_stones = self.p1_state.stones_in_holes();
         let p2_stones = self.p2_state.stones_in_holes();
-        if p1_stones == 0 || p2_stones == 0 {
-            let p1_score = self.p1_state.store + p1_stones;
-            let p2_score = self.p2_state.store + p2_stones;
-            return Some((p1_score as i8) - (p2_score as i8)); // the game is over with this score
+        let is_over = match self.rules.empty_side_rule {
+            EmptySideRule::EndGame => p1_stones == 0 || p2_stones == 0,
+            EmptySideRule::PassTurn => self.player(self.cur_player).stones_in_holes() == 0,
+        };
+        if is_over {
+            return Some(self.score_if_ended()); // the game is over with this score
         }
         None // the game isn't over yet
     }
 
+    /// Returns the final score (for Player 1) if the game ended right now, the way
+    /// `result` scores a finished game: the stones left in the holes go to the player
+    /// whose side they're on.
+    #[must_use]
+    pub fn score_if_ended(&self) -> Score {
+        let p1_score =
+            Score::from(self.p1_state.store) + Score::from(self.p1_state.stones_in_holes());
+        let p2_score =
+            Score::from(self.p2_state.store) + Score::from(self.p2_state.stones_in_holes());
+        p1_score - p2_score
+    }
+
+    /// Returns the index of this position among all positions with the same number
+    /// of stones in holes, in `0..GameState::num_ranks(stones_in_holes)`. Only the
+    /// holes and the current player are considered; the stores are ignored.
+    #[must_use]
+    pub fn rank(&self) -> u64 {
+        let holes = self.p1_state.holes.iter().chain(&self.p2_state.holes);
+        let mut remaining_stones =
+            u64::from(self.p1_state.stones_in_holes()) + u64::from(self.p2_state.stones_in_holes());
+        let mut remaining_holes = (HOLES_PER_SIDE * 2) as u64;
+
+        // rank the distribution of stones in the holes (a composition of the total)
+        let mut holes_rank = 0;
+        for &stones in holes {
+            remaining_holes -= 1;
+            for skipped_stones in 0..u64::from(stones) {
+                holes_rank += num_distributions(remaining_stones - skipped_stones, remaining_holes);
+            }
+            remaining_stones -= u64::from(stones);
+        }
+
+        holes_rank * 2 + u64::from(self.cur_player == Player::Player2)
+    }
+
+    /// The inverse of `rank`: returns the position with the given number of stones
+    /// in holes and rank. The stores are empty, and the rules are the defaults.
+    /// Panics if `rank >= GameState::num_ranks(stones_in_holes)`.
+    #[must_use]
+    pub fn unrank(stones_in_holes: u8, rank: u64) -> GameState {
+        assert!(rank < Self::num_ranks(stones_in_holes), "rank out of range: {rank}");
+
+        let cur_player = if rank.is_multiple_of(2) {
+            Player::Player1
+        } else {
+            Player::Player2
+        };
+        let mut holes_rank = rank / 2;
+        let mut remaining_stones = u64::from(stones_in_holes);
+        let mut remaining_holes = (HOLES_PER_SIDE * 2) as u64;
+
+        let mut holes = [0; HOLES_PER_SIDE * 2];
+        for hole in &mut holes {
+            remaining_holes -= 1;
+            let mut stones = 0;
+            loop {
+                let num_skipped = num_distributions(remaining_stones - stones, remaining_holes);
+                if holes_rank < num_skipped {
+                    break;
+                }
+                holes_rank -= num_skipped;
+                stones += 1;
+            }
+            *hole = stones as u8;
+            remaining_stones -= stones;
+        }
+
+        let (p1_holes, p2_holes) = holes.split_at(HOLES_PER_SIDE);
+        GameState {
+            cur_player,
+            p1_state: PlayerState::new(p1_holes.try_into().unwrap(), 0),
+            p2_state: PlayerState::new(p2_holes.try_into().unwrap(), 0),
+            swap_option: SwapOption::Unavailable,
+            rules: Rules::default(),
+        }
+    }
+
+    /// Returns the number of positions (ignoring stores) with the given number of
+    /// stones in holes.
+    #[must_use]
+    pub fn num_ranks(stones_in_holes: u8) -> u64 {
+        num_distributions(u64::from(stones_in_holes), (HOLES_PER_SIDE * 2) as u64) * 2
+    }
+
+    /// Returns the outcome of the game, or None if the game is not yet over
+    /// in this state.
+    #[must_use]
+    pub fn outcome(&self) -> Option<GameOutcome> {
+        self.result().map(GameOutcome::from_score)
+    }
+
+    /// Returns the decided margin if the game is already decided, even if it isn't
+    /// over: one player's store leads the other's by more than all of the stones left
+    /// in the holes, so the trailing player can't catch up. The margin is the smallest
+    /// final score (for Player 1, so negative if Player 2 has won) that the rest of the
+    /// game could produce. Never decided while swapping sides is still possible.
+    #[must_use]
+    pub fn is_decided(&self) -> Option<Score> {
+        if self.swap_option != SwapOption::Unavailable {
+            return None;
+        }
+        let remaining = Score::from(self.p1_state.stones_in_holes())
+            + Score::from(self.p2_state.stones_in_holes());
+        let lead = Score::from(self.p1_state.store) - Score::from(self.p2_state.store);
+        if lead > remaining {
+            Some(lead - remaining)
+        } else if -lead > remaining {
+            Some(lead + remaining)
+        } else {
+            None
+        }
+    }
+
+    /// Returns whether the current player may make the given move: the game
+    /// isn't over, and either the move is a non-empty hole or it's `SWAP_MOVE` and
+    /// swapping is available. Holes are always indexed on the current player's side.
+    #[must_use]
+    pub fn is_legal_move(&self, hole: usize) -> bool {
+        let holes = &self.player(self.cur_player).holes;
+        self.result().is_none()
+            && match hole {
+                SWAP_MOVE => self.swap_option == SwapOption::Available,
+                _ => holes.get(hole).is_some_and(|&stones| stones > 0),
+            }
+    }
+
     /// Returns an iterator over the valid moves that can be made from this
-    /// state, in ascending order.
+    /// state, in ascending order (so `SWAP_MOVE` comes last, if available).
     pub fn valid_moves(&self) -> impl Iterator<Item = usize> + '_ {
-        self.player(self.cur_player).non_empty_holes()
+        let swap_move = (self.swap_option == SwapOption::Available).then_some(SWAP_MOVE);
+        self.player(self.cur_player)
+            .non_empty_holes()
+            .chain(swap_move)
+    }
+
+    /// Returns the total number of stones on the board, including the stores. This is
+    /// a `usize` (like `TOTAL_STONES`) rather than a `u8` since it's used to validate
+    /// states from outside, whose totals haven't been checked yet and could wrap a `u8`.
+    #[must_use]
+    pub fn total_stones(&self) -> usize {
+        [&self.p1_state, &self.p2_state]
+            .into_iter()
+            .map(|player_state| {
+                usize::from(player_state.store) + usize::from(player_state.stones_in_holes())
+            })
+            .sum()
+    }
+
+    /// Returns a compact text notation for this state, which `from_notation` parses:
+    /// each player's holes (from index 0) and then store, separated by commas, with a
+    /// slash between the players; then whose turn it is (1 or 2); then a flag for
+    /// each non-default rule. The initial position is `4,4,4,4,4,4,0/4,4,4,4,4,4,0 1`.
+    #[must_use]
+    pub fn to_notation(&self) -> String {
+        let side = |player_state: &PlayerState| {
+            player_state
+                .holes
+                .iter()
+                .chain([&player_state.store])
+                .map(u8::to_string)
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        let turn = match self.cur_player {
+            Player::Player1 => "1",
+            Player::Player2 => "2",
+        };
+        let mut notation = format!("{}/{} {turn}", side(&self.p1_state), side(&self.p2_state));
+        let flags = [
+            (self.swap_option == SwapOption::Pending).then_some("pie"),
+            (self.swap_option == SwapOption::Available).then_some("swap"),
+            (self.rules.store_rule == StoreRule::SowIntoBoth).then_some("sow-both"),
+            (self.rules.capture_rule == CaptureRule::NoCaptures).then_some("no-captures"),
+            (self.rules.capture_rule == CaptureRule::EvenIfOppositeEmpty)
+                .then_some("empty-captures"),
+            (self.rules.empty_side_rule == EmptySideRule::PassTurn).then_some("pass"),
+            (self.rules.ruleset == Ruleset::Oware).then_some("oware"),
+            (self.rules.ruleset == Ruleset::Relay).then_some("relay"),
+        ];
+        for flag in flags.into_iter().flatten() {
+            notation.push(' ');
+            notation.push_str(flag);
+        }
+        notation
+    }
+
+    /// Parses a state in the notation produced by `to_notation`, returning a
+    /// description of the problem if it's invalid.
+    pub fn from_notation(notation: &str) -> Result<GameState, String> {
+        let parse_side = |side: &str| -> Result<PlayerState, String> {
+            let counts = side
+                .split(',')
+                .map(|count| {
+                    count
+                        .trim()
+                        .parse::<u8>()
+                        .map_err(|_| format!("invalid stone count {count:?}"))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            match counts.split_last() {
+                Some((&store, holes)) if holes.len() == HOLES_PER_SIDE => {
+                    PlayerState::try_from(PlayerStateRepr {
+                        holes: holes.try_into().unwrap(),
+                        store,
+                    })
+                }
+                _ => Err(format!(
+                    "expected {} stone counts per side, got {}",
+                    HOLES_PER_SIDE + 1,
+                    counts.len(),
+                )),
+            }
+        };
+
+        let mut tokens = notation.split_whitespace();
+        let board = tokens.next().ok_or("the position is empty")?;
+        let (p1_side, p2_side) = board
+            .split_once('/')
+            .ok_or("expected a '/' between the players' sides")?;
+        let mut game_state = GameState {
+            cur_player: match tokens.next() {
+                Some("1") => Player::Player1,
+                Some("2") => Player::Player2,
+                _ => return Err("expected whose turn it is (1 or 2)".to_string()),
+            },
+            p1_state: parse_side(p1_side)?,
+            p2_state: parse_side(p2_side)?,
+            swap_option: SwapOption::Unavailable,
+            rules: Rules::default(),
+        };
+        for flag in tokens {
+            match flag {
+                "pie" => game_state.swap_option = SwapOption::Pending,
+                "swap" => game_state.swap_option = SwapOption::Available,
+                "sow-both" => game_state.rules.store_rule = StoreRule::SowIntoBoth,
+                "no-captures" => game_state.rules.capture_rule = CaptureRule::NoCaptures,
+                "empty-captures" => {
+                    game_state.rules.capture_rule = CaptureRule::EvenIfOppositeEmpty;
+                }
+                "pass" => game_state.rules.empty_side_rule = EmptySideRule::PassTurn,
+                "oware" => game_state.rules.ruleset = Ruleset::Oware,
+                "relay" => game_state.rules.ruleset = Ruleset::Relay,
+                _ => return Err(format!("unknown flag {flag:?}")),
+            }
+        }
+
+        let total_stones = game_state.total_stones();
+        if total_stones > TOTAL_STONES {
+            return Err(format!(
+                "the position has {total_stones} stones, but at most {TOTAL_STONES} are allowed",
+            ));
+        }
+        Ok(game_state)
+    }
+
+    /// Returns a copy of this state with the given move applied.
+    /// Panics under the same conditions as `make_move`.
+    #[must_use]
+    pub fn after_move(&self, hole: usize) -> GameState {
+        let mut next_state = self.clone();
+        next_state.make_move(hole);
+        next_state
+    }
+
+    /// Returns an iterator over the valid moves that can be made from this
+    /// state (in ascending order), each paired with the resulting state.
+    /// States are only cloned as the iterator is advanced.
+    pub fn successors(&self) -> impl Iterator<Item = (usize, GameState)> + '_ {
+        self.valid_moves().map(|hole| (hole, self.after_move(hole)))
+    }
+}
+
+/// Returns the number of ways to distribute `stones` stones among `holes` holes.
+#[must_use]
+fn num_distributions(stones: u64, holes: u64) -> u64 {
+    if holes == 0 {
+        return u64::from(stones == 0);
+    }
+
+    // the binomial coefficient C(stones + holes - 1, holes - 1)
+    let n = stones + holes - 1;
+    let k = (holes - 1).min(stones);
+    (1..=k).fold(1, |acc, i| acc * (n - k + i) / i)
+}
+
+/// Why a move couldn't be made by `GameState::try_make_move`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MoveErrorReason {
+    /// The game is already over.
+    GameOver,
+
+    /// The hole index is beyond `SWAP_MOVE`.
+    OutOfRange,
+
+    /// The selected hole has no stones.
+    EmptyHole,
+
+    /// The move was `SWAP_MOVE`, but swapping isn't available.
+    SwapUnavailable,
+}
+
+/// An illegal move, as returned by `GameState::try_make_move`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MoveError {
+    /// The move that was attempted (a hole index, or `SWAP_MOVE`).
+    pub hole: usize,
+
+    pub reason: MoveErrorReason,
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self.reason {
+            MoveErrorReason::GameOver => "the game is over",
+            MoveErrorReason::OutOfRange => "there is no such hole",
+            MoveErrorReason::EmptyHole => "the hole is empty",
+            MoveErrorReason::SwapUnavailable => "swapping is not available",
+        };
+        write!(f, "illegal move {}: {reason}", self.hole)
+    }
+}
+
+impl std::error::Error for MoveError {}
+
+/// Where the last stone of a move lands.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Landing {
+    /// The given hole on the given player's side.
+    Hole(Player, usize),
+
+    /// The given player's store.
+    Store(Player),
+}
+
+/// What sowing the stones of a move did.
+struct Sowing {
+    /// The number of the opponent's stones captured.
+    captured: u8,
+
+    /// A bitmask of the opponent's holes that stones were captured from (bit `i` for
+    /// hole `i`).
+    captured_holes: u8,
+
+    /// Where the last stone landed, or None for `SWAP_MOVE`.
+    landing: Option<Landing>,
+}
+
+/// What a move does, beyond sowing (see `GameState::move_effects`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MoveEffects {
+    /// Whether the last stone lands in the mover's store, earning another turn.
+    pub extra_turn: bool,
+
+    /// The number of the opponent's stones captured.
+    pub captured: u8,
+
+    /// A bitmask of the opponent's holes that stones were captured from (bit `i` for
+    /// hole `i`); see `captures_from`.
+    pub captured_holes: u8,
+
+    /// Whether the move ends the game.
+    pub ends_game: bool,
+
+    /// Where the last stone lands, or None for `SWAP_MOVE`. (A landing hole on the
+    /// mover's side may have been emptied again by a capture.)
+    pub landing: Option<Landing>,
+}
+
+impl MoveEffects {
+    /// Returns whether the move captures the stones in the given hole of the opponent's.
+    #[must_use]
+    pub fn captures_from(&self, hole: usize) -> bool {
+        self.captured_holes & (1 << hole) != 0
+    }
+}
+
+/// A builder for arbitrary game states (e.g. test positions), starting from the
+/// initial position:
+///
+/// ```ignore
+/// let game_state = GameStateBuilder::new()
+///     .p1_holes([0, 0, 1, 0, 0, 0])
+///     .p1_store(20)
+///     .turn(Player::Player2)
+///     .build();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct GameStateBuilder {
+    game_state: GameState,
+}
+
+impl GameStateBuilder {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets Player 1's holes.
+    #[must_use]
+    pub fn p1_holes(mut self, holes: [u8; HOLES_PER_SIDE]) -> Self {
+        self.game_state.p1_state = PlayerState::new(holes, self.game_state.p1_state.store);
+        self
+    }
+
+    /// Sets Player 1's store.
+    #[must_use]
+    pub fn p1_store(mut self, store: u8) -> Self {
+        self.game_state.p1_state.store = store;
+        self
+    }
+
+    /// Sets Player 2's holes.
+    #[must_use]
+    pub fn p2_holes(mut self, holes: [u8; HOLES_PER_SIDE]) -> Self {
+        self.game_state.p2_state = PlayerState::new(holes, self.game_state.p2_state.store);
+        self
+    }
+
+    /// Sets Player 2's store.
+    #[must_use]
+    pub fn p2_store(mut self, store: u8) -> Self {
+        self.game_state.p2_state.store = store;
+        self
+    }
+
+    /// Sets whose turn it is.
+    #[must_use]
+    pub fn turn(mut self, player: Player) -> Self {
+        self.game_state.cur_player = player;
+        self
+    }
+
+    /// Sets the state of the pie rule.
+    #[must_use]
+    pub fn swap_option(mut self, swap_option: SwapOption) -> Self {
+        self.game_state.swap_option = swap_option;
+        self
+    }
+
+    /// Sets the rules.
+    #[must_use]
+    pub fn rules(mut self, rules: Rules) -> Self {
+        self.game_state.rules = rules;
+        self
+    }
+
+    /// Returns the built state. Panics if it has more stones in total than a game
+    /// starts with, since the scores and `PackedState` rely on that bound.
+    #[must_use]
+    pub fn build(self) -> GameState {
+        let total_stones = self.game_state.total_stones();
+        assert!(
+            total_stones <= TOTAL_STONES,
+            "the state has {total_stones} stones, but at most {TOTAL_STONES} are allowed",
+        );
+        self.game_state
     }
 }
 
 /// Represents the state for a single player (their holes and store).
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(try_from = "PlayerStateRepr", into = "PlayerStateRepr")
+)]
 pub struct PlayerState {
     /// The player's holes. Index 0 is closest to this player's store.
-    pub holes: [u8; HOLES_PER_SIDE],
+    /// The total and the non-empty holes are cached, so the holes are only read
+    /// through `holes`; construct a new `PlayerState` to change them.
+    holes: [u8; HOLES_PER_SIDE],
 
     /// The player's store.
     pub store: u8,
+
+    /// The total number of stones in `holes`, maintained incrementally since
+    /// `GameState::result` needs it for nearly every searched node.
+    stones_in_holes: u8,
+
+    /// A bitmask of the non-empty holes (bit `i` for `holes[i]`), maintained
+    /// incrementally since the valid moves are needed for every searched node and
+    /// every rollout move.
+    non_empty_mask: u8,
+}
+
+// Assert that there's a bit in `PlayerState::non_empty_mask` for each hole.
+const_assert!(HOLES_PER_SIDE <= u8::BITS as usize);
+
+/// The serialized form of a `PlayerState` (without the cached values).
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+struct PlayerStateRepr {
+    holes: [u8; HOLES_PER_SIDE],
+    store: u8,
+}
+
+impl TryFrom<PlayerStateRepr> for PlayerState {
+    type Error = String;
+
+    /// Checks the stone counts first, since they come from outside (a file, the
+    /// network, or typed-in notation) and could overflow the cached total.
+    fn try_from(repr: PlayerStateRepr) -> Result<Self, Self::Error> {
+        let total_stones = repr
+            .holes
+            .iter()
+            .try_fold(repr.store, |total, &stones| total.checked_add(stones));
+        match total_stones {
+            Some(total_stones) if usize::from(total_stones) <= TOTAL_STONES => {
+                Ok(PlayerState::new(repr.holes, repr.store))
+            }
+            _ => Err(format!("a side has more than the {TOTAL_STONES} allowed stones")),
+        }
+    }
+}
+
+impl From<PlayerState> for PlayerStateRepr {
+    fn from(player_state: PlayerState) -> Self {
+        PlayerStateRepr {
+            holes: player_state.holes,
+            store: player_state.store,
+        }
+    }
 }
 
 impl Default for PlayerState {
     fn default() -> Self {
-        Self {
-            holes: [INITIAL_STONES_PER_HOLE; HOLES_PER_SIDE],
-            store: 0,
-        }
+        PlayerState::new([INITIAL_STONES_PER_HOLE; HOLES_PER_SIDE], 0)
     }
 }
 
 impl PlayerState {
+    #[must_use]
+    pub fn new(holes: [u8; HOLES_PER_SIDE], store: u8) -> Self {
+        Self {
+            holes,
+            store,
+            stones_in_holes: holes.iter().sum(),
+            non_empty_mask: non_empty_mask(&holes),
+        }
+    }
+
+    /// Returns the number of stones in each of the player's holes. Index 0 is closest
+    /// to this player's store.
+    #[must_use]
+    pub fn holes(&self) -> &[u8; HOLES_PER_SIDE] {
+        &self.holes
+    }
+
     /// Returns the total number of stones in the holes on this player's side.
     #[must_use]
     pub fn stones_in_holes(&self) -> u8 {
-        self.holes.iter().sum()
+        self.stones_in_holes
+    }
+
+    /// Empties the given hole, returning the number of stones that were in it.
+    fn take_stones(&mut self, hole: usize) -> u8 {
+        let stones = mem::take(&mut self.holes[hole]);
+        self.stones_in_holes -= stones;
+        self.non_empty_mask &= !(1 << hole);
+        stones
+    }
+
+    /// Adds a stone to the given hole.
+    fn add_stone(&mut self, hole: usize) {
+        self.holes[hole] += 1;
+        self.stones_in_holes += 1;
+        self.non_empty_mask |= 1 << hole;
+    }
+
+    /// Returns whether the cached `stones_in_holes` and `non_empty_mask` match the holes.
+    #[must_use]
+    fn is_cache_consistent(&self) -> bool {
+        self.stones_in_holes == self.holes.iter().sum::<u8>()
+            && self.non_empty_mask == non_empty_mask(&self.holes)
     }
 
     /// Returns this player's score (assuming this state is at the end of a game).
@@ -205,11 +1221,531 @@ impl PlayerState {
 
     /// Returns an iterator over the indices of the non-empty holes on this
     /// player's side, in ascending order.
-    pub fn non_empty_holes(&self) -> impl Iterator<Item = usize> + '_ {
-        self.holes
-            .iter()
-            .enumerate()
-            .filter(|(_i, h)| **h > 0)
-            .map(|(i, _)| i)
+    pub fn non_empty_holes(&self) -> impl Iterator<Item = usize> {
+        let mut mask = self.non_empty_mask;
+        iter::from_fn(move || {
+            (mask != 0).then(|| {
+                let hole = mask.trailing_zeros() as usize;
+                mask &= mask - 1; // clear the lowest set bit
+                hole
+            })
+        })
+    }
+
+    /// Returns the number of non-empty holes on this player's side.
+    #[must_use]
+    pub fn num_non_empty_holes(&self) -> usize {
+        self.non_empty_mask.count_ones() as usize
+    }
+}
+
+/// Returns a bitmask of the non-empty holes (see `PlayerState::non_empty_mask`).
+#[must_use]
+fn non_empty_mask(holes: &[u8; HOLES_PER_SIDE]) -> u8 {
+    holes
+        .iter()
+        .enumerate()
+        .filter(|(_, &stones)| stones > 0)
+        .fold(0, |mask, (hole, _)| mask | (1 << hole))
+}
+
+/// The number of bits used for each stone count in a `PackedState`.
+const PACKED_BITS_PER_COUNT: u32 = 7;
+
+/// The number of bits used for the `SwapOption` in a `PackedState`.
+const PACKED_SWAP_OPTION_BITS: u32 = 2;
+
+// Assert that any stone count fits in the packed representation (which is implied by the total
+// number of stones fitting), and that all of the counts plus the turn, swap option, and rules
+// fit in a u128.
+const_assert!(TOTAL_STONES < 1 << PACKED_BITS_PER_COUNT);
+const_assert!(
+    (HOLES_PER_SIDE as u32 + 1) * 2 * PACKED_BITS_PER_COUNT
+        + 1
+        + PACKED_SWAP_OPTION_BITS
+        + Rules::NUM_BITS
+        <= u128::BITS
+);
+
+/// A `GameState` packed into a single integer, which is much cheaper to hash and
+/// compare than the full struct. (14 stone counts of 7 bits each don't fit in a u64,
+/// hence the u128.)
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct PackedState(u128);
+
+impl PackedState {
+    /// Returns the raw packed integer.
+    #[must_use]
+    pub fn to_bits(self) -> u128 {
+        self.0
+    }
+
+    /// Creates a `PackedState` from a raw packed integer, as returned by `to_bits`.
+    #[must_use]
+    pub fn from_bits(bits: u128) -> Self {
+        PackedState(bits)
+    }
+}
+
+impl From<&GameState> for PackedState {
+    fn from(game_state: &GameState) -> Self {
+        let counts = [&game_state.p1_state, &game_state.p2_state]
+            .into_iter()
+            .flat_map(|player_state| player_state.holes.iter().chain([&player_state.store]));
+
+        let mut packed = game_state.rules.to_bits();
+        packed = (packed << PACKED_SWAP_OPTION_BITS) | game_state.swap_option as u128;
+        packed = (packed << 1) | u128::from(game_state.cur_player == Player::Player2);
+        for &count in counts {
+            packed = (packed << PACKED_BITS_PER_COUNT) | u128::from(count);
+        }
+        PackedState(packed)
+    }
+}
+
+impl From<PackedState> for GameState {
+    fn from(packed_state: PackedState) -> Self {
+        let mut packed = packed_state.0;
+        let mut take_count = || {
+            let count = packed & ((1 << PACKED_BITS_PER_COUNT) - 1);
+            packed >>= PACKED_BITS_PER_COUNT;
+            count as u8
+        };
+
+        // the counts were packed starting from the most significant end, so unpack in reverse
+        let mut unpack_player_state = || {
+            let store = take_count();
+            let mut holes = [0; HOLES_PER_SIDE];
+            for hole in holes.iter_mut().rev() {
+                *hole = take_count();
+            }
+            PlayerState::new(holes, store)
+        };
+        let p2_state = unpack_player_state();
+        let p1_state = unpack_player_state();
+
+        let cur_player = if packed & 1 == 0 {
+            Player::Player1
+        } else {
+            Player::Player2
+        };
+        packed >>= 1;
+        let swap_option = match packed & ((1 << PACKED_SWAP_OPTION_BITS) - 1) {
+            0 => SwapOption::Unavailable,
+            1 => SwapOption::Pending,
+            _ => SwapOption::Available,
+        };
+        let rules = Rules::from_bits(packed >> PACKED_SWAP_OPTION_BITS);
+        GameState {
+            cur_player,
+            p1_state,
+            p2_state,
+            swap_option,
+            rules,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, seq::IteratorRandom, SeedableRng};
+
+    use super::*;
+
+    /// Asserts each player's holes and store, and whose turn it is.
+    fn assert_state(
+        game_state: &GameState,
+        p1: ([u8; HOLES_PER_SIDE], u8),
+        p2: ([u8; HOLES_PER_SIDE], u8),
+        cur_player: Player,
+    ) {
+        assert_eq!((*game_state.p1_state.holes(), game_state.p1_state.store), p1);
+        assert_eq!((*game_state.p2_state.holes(), game_state.p2_state.store), p2);
+        assert_eq!(game_state.cur_player, cur_player);
+    }
+
+    #[test]
+    fn sowing_wraps_around_past_the_opponents_store() {
+        let mut game_state = GameStateBuilder::new()
+            .p1_holes([1, 1, 1, 1, 1, 14])
+            .p2_holes([1, 1, 1, 1, 1, 1])
+            .build();
+        game_state.make_move(5);
+        // 5 stones in the other holes, 1 in the store, 6 on the opponent's side, and
+        // then back around to holes 5 and 4 (which wasn't empty, so nothing is captured)
+        assert_state(
+            &game_state,
+            ([2, 2, 2, 2, 3, 1], 1),
+            ([2, 2, 2, 2, 2, 2], 0),
+            Player::Player2,
+        );
+    }
+
+    #[test]
+    fn sowing_into_both_stores_laps_through_the_opponents_store() {
+        let mut game_state = GameStateBuilder::new()
+            .p1_holes([1, 1, 1, 1, 1, 14])
+            .p2_holes([1, 1, 1, 1, 1, 1])
+            .rules(Rules {
+                store_rule: StoreRule::SowIntoBoth,
+                ..Rules::default()
+            })
+            .build();
+        game_state.make_move(5);
+        // 5 stones in the other holes, 1 in each store and 6 on the opponent's side, so
+        // the last stone lands back in the emptied hole 5 and captures the opposite hole
+        assert_state(
+            &game_state,
+            ([2, 2, 2, 2, 2, 0], 4),
+            ([0, 2, 2, 2, 2, 2], 1),
+            Player::Player2,
+        );
+    }
+
+    #[test]
+    fn last_stone_in_the_store_earns_an_extra_turn() {
+        let mut game_state = GameStateBuilder::new().build();
+        game_state.make_move(3);
+        assert_state(
+            &game_state,
+            ([5, 5, 5, 0, 4, 4], 1),
+            ([4, 4, 4, 4, 4, 4], 0),
+            Player::Player1,
+        );
+    }
+
+    #[test]
+    fn extra_turns_are_found_after_lapping_the_board() {
+        let state_with = |stones, store_rule| {
+            GameStateBuilder::new()
+                .p1_holes([0, 0, 0, 0, 0, stones])
+                .p2_holes([1, 1, 1, 1, 1, 1])
+                .rules(Rules { store_rule, ..Rules::default() })
+                .build()
+        };
+
+        // a full lap is 13 stones, skipping the opponent's store
+        let game_state = state_with(19, StoreRule::SkipOpponents);
+        assert!(game_state.move_effects(5).extra_turn);
+        let mut next_state = game_state.clone();
+        next_state.make_move(5);
+        assert_state(
+            &next_state,
+            ([2, 2, 2, 2, 2, 1], 2),
+            ([2, 2, 2, 2, 2, 2], 0),
+            Player::Player1,
+        );
+        assert!(
+            !state_with(18, StoreRule::SkipOpponents)
+                .move_effects(5)
+                .extra_turn
+        );
+
+        // or 14 stones through both stores, where landing in the opponent's doesn't count
+        assert!(
+            state_with(20, StoreRule::SowIntoBoth)
+                .move_effects(5)
+                .extra_turn
+        );
+        assert!(
+            !state_with(13, StoreRule::SowIntoBoth)
+                .move_effects(5)
+                .extra_turn
+        );
+    }
+
+    #[test]
+    fn last_stone_in_an_empty_hole_captures_the_opposite_hole() {
+        let mut game_state = GameStateBuilder::new().p1_holes([1, 0, 0, 0, 1, 0]).build();
+        let effects = game_state.move_effects(4);
+        assert_eq!(effects.landing, Some(Landing::Hole(Player::Player1, 3)));
+        assert_eq!((effects.captured, effects.captured_holes), (4, 1 << 2));
+
+        game_state.make_move(4);
+        // hole 3 is opposite the opponent's hole 2; both its 4 stones and the landing
+        // stone go to the store
+        assert_state(
+            &game_state,
+            ([1, 0, 0, 0, 0, 0], 5),
+            ([4, 4, 0, 4, 4, 4], 0),
+            Player::Player2,
+        );
+    }
+
+    #[test]
+    fn no_capture_when_the_opposite_hole_is_empty() {
+        let mut game_state = GameStateBuilder::new()
+            .p1_holes([1, 0, 0, 0, 1, 0])
+            .p2_holes([4, 4, 0, 4, 4, 4])
+            .build();
+        game_state.make_move(4);
+        assert_state(
+            &game_state,
+            ([1, 0, 0, 1, 0, 0], 0),
+            ([4, 4, 0, 4, 4, 4], 0),
+            Player::Player2,
+        );
+    }
+
+    #[test]
+    fn no_captures_rule_disables_captures() {
+        let rules = |capture_rule| Rules { capture_rule, ..Rules::default() };
+        let mut standard = GameStateBuilder::new()
+            .p1_holes([1, 0, 0, 0, 1, 0])
+            .rules(rules(CaptureRule::Standard))
+            .build();
+        let mut no_captures = standard.clone();
+        no_captures.rules = rules(CaptureRule::NoCaptures);
+
+        standard.make_move(4);
+        assert_state(&standard, ([1, 0, 0, 0, 0, 0], 5), ([4, 4, 0, 4, 4, 4], 0), Player::Player2);
+        no_captures.make_move(4);
+        assert_state(
+            &no_captures,
+            ([1, 0, 0, 1, 0, 0], 0),
+            ([4, 4, 4, 4, 4, 4], 0),
+            Player::Player2,
+        );
+    }
+
+    /// Returns a state where Player 1's only move empties their side (and earns an
+    /// extra turn), under the given empty side rule.
+    fn emptying_move_state(empty_side_rule: EmptySideRule) -> GameState {
+        GameStateBuilder::new()
+            .p1_holes([1, 0, 0, 0, 0, 0])
+            .p1_store(10)
+            .p2_holes([0, 0, 0, 0, 3, 0])
+            .p2_store(0)
+            .rules(Rules { empty_side_rule, ..Rules::default() })
+            .build()
+    }
+
+    #[test]
+    fn empty_side_ends_the_game_under_end_game() {
+        let mut game_state = emptying_move_state(EmptySideRule::EndGame);
+        game_state.make_move(0);
+        // Player 2 gets the 3 stones left on their side
+        assert_eq!(game_state.result(), Some(11 - 3));
+    }
+
+    #[test]
+    fn empty_side_passes_the_turn_under_pass_turn() {
+        let mut game_state = emptying_move_state(EmptySideRule::PassTurn);
+        game_state.make_move(0);
+        assert_eq!(game_state.result(), None);
+        assert_eq!(game_state.cur_player, Player::Player2);
+
+        // Player 1 still has no stones, so Player 2 moves again (which isn't an extra
+        // turn, as the last stone doesn't land in their store)
+        assert!(!game_state.move_effects(4).extra_turn);
+        game_state.make_move(4);
+        assert_state(
+            &game_state,
+            ([0, 0, 0, 0, 0, 0], 11),
+            ([0, 1, 1, 1, 0, 0], 0),
+            Player::Player2,
+        );
+        assert_eq!(game_state.result(), None);
+    }
+
+    #[test]
+    fn relay_sowing_continues_from_occupied_holes() {
+        let mut game_state = GameStateBuilder::new()
+            .p1_holes([0, 0, 1, 0, 1, 1])
+            .p2_holes([1, 1, 1, 1, 1, 3])
+            .rules(Rules {
+                ruleset: Ruleset::Relay,
+                ..Rules::default()
+            })
+            .build();
+        game_state.make_move(5);
+        // hole 5's stone lands in hole 4, whose 2 stones are sown into holes 3 and 2,
+        // whose 2 stones are sown into holes 1 and 0; hole 0 was empty, so that last
+        // stone captures the opponent's hole 5
+        assert_state(
+            &game_state,
+            ([0, 1, 0, 1, 0, 0], 4),
+            ([1, 1, 1, 1, 1, 0], 0),
+            Player::Player2,
+        );
+    }
+
+    #[test]
+    fn scores_keep_their_sign_at_the_largest_margins() {
+        // all 72 stones end up with one player: full holes on their side, and the rest
+        // in their store
+        let p2_wins = GameStateBuilder::new()
+            .p1_holes([0; HOLES_PER_SIDE])
+            .p1_store(0)
+            .p2_holes([MAX_STONES_PER_HOLE; HOLES_PER_SIDE])
+            .p2_store(36)
+            .build();
+        assert_eq!(p2_wins.result(), Some(-72));
+        assert_eq!(p2_wins.outcome(), Some(GameOutcome::Player2Wins(72)));
+
+        let p1_wins = GameStateBuilder::new()
+            .p1_holes([MAX_STONES_PER_HOLE; HOLES_PER_SIDE])
+            .p1_store(36)
+            .p2_holes([0; HOLES_PER_SIDE])
+            .p2_store(0)
+            .build();
+        assert_eq!(p1_wins.result(), Some(72));
+        assert_eq!(p1_wins.outcome(), Some(GameOutcome::Player1Wins(72)));
+    }
+
+    #[test]
+    fn store_leads_beyond_the_stones_left_decide_the_game() {
+        let state_with_stores = |p1_store, p2_store| {
+            GameStateBuilder::new()
+                .p1_holes([1, 0, 2, 0, 0, 1])
+                .p1_store(p1_store)
+                .p2_holes([0, 3, 0, 0, 1, 0])
+                .p2_store(p2_store)
+                .build()
+        };
+
+        // 8 stones are left in the holes
+        assert_eq!(state_with_stores(20, 11).is_decided(), Some(1));
+        assert_eq!(state_with_stores(10, 30).is_decided(), Some(-12));
+        assert_eq!(state_with_stores(19, 11).is_decided(), None);
+        assert_eq!(state_with_stores(11, 11).is_decided(), None);
+
+        // the lead doesn't count for anything while the sides might still be swapped
+        let mut swappable = state_with_stores(20, 11);
+        swappable.swap_option = SwapOption::Available;
+        assert_eq!(swappable.is_decided(), None);
+    }
+
+    #[test]
+    fn random_starts_give_both_sides_the_same_stones() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for stones_per_side in [1, 4, 24, TOTAL_STONES as u8 / 2] {
+            let game_state = GameState::random_start(&mut rng, stones_per_side);
+            assert_eq!(game_state.p1_state, game_state.p2_state);
+            assert_eq!(game_state.p1_state.store, 0);
+            assert_eq!(game_state.p1_state.stones_in_holes(), stones_per_side);
+            assert_eq!(game_state.total_stones(), usize::from(stones_per_side) * 2);
+            assert!(game_state.result().is_none());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one stone")]
+    fn random_starts_need_stones() {
+        let _ = GameState::random_start(&mut StdRng::seed_from_u64(0), 0);
+    }
+
+    #[test]
+    fn random_games_conserve_stones() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for ruleset in [Ruleset::Kalah, Ruleset::Oware, Ruleset::Relay] {
+            let rules = Rules { ruleset, ..Rules::default() };
+            let mut game_state = GameState::new_with(rules, false, MAX_STONES_PER_HOLE).unwrap();
+            let total_stones = game_state.total_stones();
+            // Oware games can go around in circles, so cut them off eventually
+            for _ in 0..1000 {
+                if game_state.result().is_some() {
+                    break;
+                }
+                let hole = game_state.valid_moves().choose(&mut rng).unwrap();
+                game_state.make_move(hole);
+                assert_eq!(
+                    game_state.total_stones(),
+                    total_stones,
+                    "{ruleset:?}: {}",
+                    game_state.to_notation(),
+                );
+            }
+        }
+    }
+
+    /// Returns every state of a random game under each combination of rules, with and
+    /// without the pie rule (so covering every `SwapOption`).
+    fn random_game_states(rng: &mut StdRng) -> Vec<GameState> {
+        let mut game_states = Vec::new();
+        for store_rule in [StoreRule::SkipOpponents, StoreRule::SowIntoBoth] {
+            for capture_rule in [
+                CaptureRule::Standard,
+                CaptureRule::NoCaptures,
+                CaptureRule::EvenIfOppositeEmpty,
+            ] {
+                for empty_side_rule in [EmptySideRule::EndGame, EmptySideRule::PassTurn] {
+                    for ruleset in [Ruleset::Kalah, Ruleset::Oware, Ruleset::Relay] {
+                        for pie_rule in [false, true] {
+                            let rules = Rules {
+                                store_rule,
+                                capture_rule,
+                                empty_side_rule,
+                                ruleset,
+                            };
+                            let mut game_state = GameState::new(rules, pie_rule);
+                            game_states.push(game_state.clone());
+                            // Oware games can go around in circles, so cut them off
+                            for _ in 0..200 {
+                                if game_state.result().is_some() {
+                                    break;
+                                }
+                                let hole = game_state.valid_moves().choose(rng).unwrap();
+                                game_state.make_move(hole);
+                                game_states.push(game_state.clone());
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        game_states
+    }
+
+    #[test]
+    fn packed_states_unpack_to_the_same_state() {
+        let game_states = random_game_states(&mut StdRng::seed_from_u64(0));
+        for swap_option in [
+            SwapOption::Unavailable,
+            SwapOption::Pending,
+            SwapOption::Available,
+        ] {
+            assert!(game_states
+                .iter()
+                .any(|game_state| game_state.swap_option == swap_option));
+        }
+        for game_state in game_states {
+            let packed_state = PackedState::from(&game_state);
+            assert_eq!(GameState::from(packed_state), game_state);
+            assert_eq!(PackedState::from_bits(packed_state.to_bits()), packed_state);
+        }
+    }
+
+    #[test]
+    fn notation_round_trips() {
+        for game_state in random_game_states(&mut StdRng::seed_from_u64(1)) {
+            let notation = game_state.to_notation();
+            assert_eq!(GameState::from_notation(&notation), Ok(game_state), "{notation}");
+        }
+
+        for notation in [
+            "4,4,4,4,4,4,0/4,4,4,4,4,4,0 1",
+            "4,4,4,0,5,5,1/5,4,4,4,4,4,0 2 swap",
+            "1,0,2,0,0,12,20/0,3,0,0,1,0,11 2 sow-both empty-captures pass relay",
+        ] {
+            let game_state = GameState::from_notation(notation).unwrap();
+            assert_eq!(game_state.to_notation(), notation);
+        }
+    }
+
+    #[test]
+    fn sowing_wraps_around_into_the_emptied_hole_and_captures() {
+        let mut game_state = GameStateBuilder::new()
+            .p1_holes([1, 1, 1, 1, 1, 13])
+            .p2_holes([1, 1, 1, 1, 1, 1])
+            .build();
+        game_state.make_move(5);
+        // the last stone lands back in hole 5, capturing the opponent's hole 0
+        assert_state(
+            &game_state,
+            ([2, 2, 2, 2, 2, 0], 4),
+            ([0, 2, 2, 2, 2, 2], 0),
+            Player::Player2,
+        );
     }
 }
@@ -1,31 +1,298 @@
+use eframe::Theme;
 use egui::{
-    vec2, Align, Button, CentralPanel, CursorIcon, Direction, FontFamily, FontId, Frame, Label,
-    Layout, Rect, RichText, Sense, SidePanel, Slider, Stroke, TextStyle, Ui, Widget, WidgetInfo,
-    WidgetText, WidgetType,
+    plot::{Line, MarkerShape, Plot, PlotPoints, Points, VLine},
+    pos2, vec2, Align, Button, CentralPanel, ComboBox, CursorIcon, Direction, Event, FontFamily,
+    FontId, Frame, Key, Label, Layout, Rect, RichText, Sense, Shape, SidePanel, Slider, Stroke,
+    TextStyle, TopBottomPanel, Ui, Visuals, Widget, WidgetInfo, WidgetText, WidgetType,
 };
 use itertools::Itertools;
 use num_format::{Locale, ToFormattedString};
 use num_traits::{Num, NumCast};
 use rand::{seq::IteratorRandom, thread_rng};
+use serde::{Deserialize, Serialize};
+
+use std::{
+    collections::VecDeque,
+    io,
+    time::{Duration, Instant},
+};
 
 use crate::{
-    game_state::{GameState, Player, HOLES_PER_SIDE},
-    mcts::{get_best_options, OptionStats, StateStats},
-    worker::Worker,
+    analysis::explain_move,
+    export::{write_analysis_csv, DEFAULT_ANALYSIS_PATH},
+    game_state::{
+        CaptureRule, EmptySideRule, GameOutcome, GameState, Landing, Player, Rules, Ruleset,
+        StoreRule, SwapOption, HOLES_PER_SIDE, INITIAL_STONES_PER_HOLE, MAX_MOVES,
+        MAX_STONES_PER_HOLE, SWAP_MOVE, TOTAL_STONES,
+    },
+    logging,
+    mcts::{
+        num_truncated_rollouts, sample_move_with_temperature, LineStep, OptionStats, StateStats,
+        DEFAULT_CACHE_SIZE_LIMIT, DEFAULT_DISCOUNT, DEFAULT_EXPLORATION_RATE, DEFAULT_FPU,
+    },
+    net::{NetConnection, NetMessage, DEFAULT_ADDRESS},
+    opening_book::{OpeningBook, DEFAULT_BOOK_PATH},
+    tablebase::{DEFAULT_MAX_STONES, PRACTICAL_MAX_STONES},
+    worker::{Worker, DEFAULT_UPDATE_HZ},
 };
 
+/// The keys that play the current player's holes, counting out from their store.
+const HOLE_KEYS: [Key; 9] = [
+    Key::Num1,
+    Key::Num2,
+    Key::Num3,
+    Key::Num4,
+    Key::Num5,
+    Key::Num6,
+    Key::Num7,
+    Key::Num8,
+    Key::Num9,
+];
+
+/// The storage key for the user's theme preference.
+const THEME_KEY: &str = "theme";
+
+/// The number of plies of the best line shown beneath the move buttons.
+const BEST_LINE_PLIES: usize = 8;
+
+/// The storage key for the user's other settings.
+const SETTINGS_KEY: &str = "settings";
+
+/// The user's settings that persist between runs, besides the theme. The live state
+/// (the game, the search, and any connection) isn't included.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct Settings {
+    debug: bool,
+    flip_board: bool,
+    mark_extra_turns: bool,
+    difficulty: Difficulty,
+    temperature: f32,
+    deterministic: bool,
+    rules: Rules,
+    pie_rule: bool,
+    random_start: bool,
+    stones_per_hole: u8,
+    bot_think_time: f32,
+    ponder_replies: bool,
+    pause_when_unfocused: bool,
+    tablebase_stones: u8,
+    cache_size_limit: usize,
+    use_rave: bool,
+    discount: f32,
+    exploration_rate: f32,
+    update_hz: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            debug: false,
+            flip_board: false,
+            mark_extra_turns: false,
+            difficulty: Difficulty::Hard,
+            temperature: 0.0,
+            deterministic: false,
+            rules: Rules::default(),
+            pie_rule: false,
+            random_start: false,
+            stones_per_hole: INITIAL_STONES_PER_HOLE,
+            bot_think_time: 1.0,
+            ponder_replies: false,
+            pause_when_unfocused: true,
+            tablebase_stones: DEFAULT_MAX_STONES,
+            cache_size_limit: DEFAULT_CACHE_SIZE_LIMIT,
+            use_rave: false,
+            discount: 1.0,
+            exploration_rate: DEFAULT_EXPLORATION_RATE,
+            update_hz: DEFAULT_UPDATE_HZ,
+        }
+    }
+}
+
+/// The file that games are saved to and loaded from.
+const SAVED_GAME_PATH: &str = "saved_game.json";
+
+/// A game as saved to `SAVED_GAME_PATH`: the history, and which state in it is active.
+#[derive(Serialize, Deserialize)]
+struct SavedGame {
+    history: Vec<(Option<usize>, GameState, Option<f32>)>,
+    active_state_index: usize,
+}
+
+/// The user's choice of color theme.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+enum ThemePreference {
+    /// Follow the operating system's theme.
+    System,
+    Light,
+    Dark,
+}
+
+impl ThemePreference {
+    /// Returns the visuals for this theme, given the system theme (if known).
+    #[must_use]
+    fn visuals(self, system_theme: Option<Theme>) -> Visuals {
+        match self {
+            ThemePreference::System => system_theme.unwrap_or(Theme::Dark).egui_visuals(),
+            ThemePreference::Light => Visuals::light(),
+            ThemePreference::Dark => Visuals::dark(),
+        }
+    }
+}
+
+/// A preset for how strongly the bot plays.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    /// Returns the rollout budget for the active state and the move selection
+    /// temperature used at this difficulty.
+    #[must_use]
+    fn settings(self) -> (Option<u32>, f32) {
+        match self {
+            Difficulty::Easy => (Some(2_000), 1.0),
+            Difficulty::Medium => (Some(50_000), 0.3),
+            Difficulty::Hard => (None, 0.0),
+        }
+    }
+}
+
+/// Who makes a player's moves.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Controller {
+    Human,
+    Bot,
+}
+
 pub struct MancalaApp {
     /// Whether UI debug mode is enabled.
     debug: bool,
 
-    /// The history of game states.
-    history: Vec<GameState>,
+    /// The selected color theme.
+    theme: ThemePreference,
+
+    /// Whether the board is flipped so that Player 2's side is at the bottom.
+    flip_board: bool,
+
+    /// Whether to outline the holes whose moves earn an extra turn.
+    mark_extra_turns: bool,
+
+    /// Whether the game is only displayed, for spectating: the board can't be played
+    /// on and the move buttons are hidden, but the bot and a network opponent still
+    /// move.
+    spectate: bool,
+
+    /// Whether to highlight the recommended move for the active state.
+    show_hint: bool,
+
+    /// The selected difficulty preset.
+    difficulty: Difficulty,
+
+    /// The temperature used to pick the bot's move (0 always picks a best move).
+    temperature: f32,
+
+    /// Whether the bot always picks the same best move (see
+    /// `StateStats::best_option`), ignoring `temperature`.
+    deterministic: bool,
+
+    /// The rules for new games.
+    rules: Rules,
+
+    /// Whether new games are played with the pie rule (see `SwapOption`).
+    pie_rule: bool,
+
+    /// Whether new games start with the stones scattered randomly (see
+    /// `GameState::random_start`).
+    random_start: bool,
+
+    /// The number of stones in each hole at the start of new games.
+    stones_per_hole: u8,
+
+    /// Who makes Player 1's moves.
+    player1_controller: Controller,
+
+    /// Who makes Player 2's moves.
+    player2_controller: Controller,
+
+    /// How long the bot thinks before making each move, in seconds.
+    bot_think_time: f32,
+
+    /// When the bot started thinking about its current move, if it's the bot's turn.
+    bot_turn_start: Option<Instant>,
+
+    /// Whether to show the search tree panel.
+    show_tree: bool,
+
+    /// Whether to show the panel listing the moves in the history.
+    show_history: bool,
+
+    /// Whether to show the graph of the evaluation over the history.
+    show_eval_graph: bool,
+
+    /// The number of plies of the principal line to expand in the search tree panel.
+    tree_plies: usize,
+
+    /// The number of rollouts to search the active state for when analyzing it.
+    analysis_target: u32,
+
+    /// The rollout target of the analysis in progress, if any. While analyzing, the
+    /// worker searches only the active state.
+    analysis: Option<u32>,
+
+    /// Whether the worker should also search the positions after each possible move.
+    ponder_replies: bool,
+
+    /// The maximum number of stones in holes for the worker's tablebase (see
+    /// `Worker::set_tablebase_stones`).
+    tablebase_stones: u8,
+
+    /// Whether to pause the worker while the window isn't focused (unless the bot is
+    /// thinking about its move).
+    pause_when_unfocused: bool,
+
+    /// A position and the move whose result is being previewed (by shift-clicking a
+    /// hole), if any. The preview only shows while that position is the active state.
+    peek: Option<(GameState, usize)>,
+
+    /// The history of game states, each with the move that led to it (None for the
+    /// first state, or a position that was loaded or received) and the evaluation for
+    /// Player 1 when the move from it was made (see `p1_evaluation`), if any.
+    history: Vec<(Option<usize>, GameState, Option<f32>)>,
 
     /// The index of the active game state in `self.history`.
     active_state_index: usize,
 
     /// The manager for the worker thread.
     worker: Worker,
+
+    /// Precomputed best moves for early-game states.
+    opening_book: OpeningBook,
+
+    /// A message describing the result of the last analysis export, if any.
+    export_status: Option<String>,
+
+    /// The text field for loading a position (see `GameState::to_notation`).
+    position_text: String,
+
+    /// Why the last position couldn't be loaded, if it couldn't.
+    position_error: Option<String>,
+
+    /// A message describing the result of the last game save or load, if any.
+    save_status: Option<String>,
+
+    /// The address to host or join a network game at.
+    net_address: String,
+
+    /// The connection to the other instance in a network game, if any.
+    net: Option<NetConnection>,
+
+    /// A description of the error from the last attempt to host a game, if any.
+    net_status: Option<String>,
 }
 
 impl MancalaApp {
@@ -34,15 +301,100 @@ impl MancalaApp {
     pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
         MancalaApp::set_styles(&cc.egui_ctx);
 
+        let theme = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, THEME_KEY))
+            .unwrap_or(ThemePreference::System);
+        cc.egui_ctx
+            .set_visuals(theme.visuals(cc.integration_info.system_theme));
+
+        let settings: Settings = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, SETTINGS_KEY))
+            .unwrap_or_default();
+        logging::set_verbose(settings.debug);
+
         let initial_game_state = GameState::default();
-        let worker = Worker::spawn(&cc.egui_ctx, 2_000_000);
+        let worker = Worker::spawn(&cc.egui_ctx, settings.cache_size_limit);
+        worker.set_use_rave(settings.use_rave);
+        worker.set_discount(settings.discount);
+        worker.set_exploration_rate(settings.exploration_rate);
+        worker.set_update_hz(settings.update_hz);
+        worker.set_rollout_budget(settings.difficulty.settings().0);
+        let tablebase_stones = settings.tablebase_stones.min(PRACTICAL_MAX_STONES);
+        if tablebase_stones != DEFAULT_MAX_STONES {
+            worker.set_tablebase_stones(tablebase_stones);
+        }
         worker.set_active_state(initial_game_state.clone());
+        worker.set_line_plies(BEST_LINE_PLIES);
 
-        Self {
-            debug: false,
-            history: vec![initial_game_state],
+        let mut app = Self {
+            debug: settings.debug,
+            theme,
+            flip_board: settings.flip_board,
+            mark_extra_turns: settings.mark_extra_turns,
+            spectate: false,
+            show_hint: false,
+            difficulty: settings.difficulty,
+            temperature: settings.temperature,
+            deterministic: settings.deterministic,
+            rules: settings.rules,
+            pie_rule: settings.pie_rule,
+            random_start: settings.random_start,
+            stones_per_hole: settings.stones_per_hole.clamp(1, MAX_STONES_PER_HOLE),
+            player1_controller: Controller::Human,
+            player2_controller: Controller::Human,
+            bot_think_time: settings.bot_think_time,
+            bot_turn_start: None,
+            show_tree: false,
+            show_history: false,
+            show_eval_graph: false,
+            tree_plies: 3,
+            analysis_target: 5_000_000,
+            analysis: None,
+            ponder_replies: settings.ponder_replies,
+            tablebase_stones,
+            pause_when_unfocused: settings.pause_when_unfocused,
+            peek: None,
+            history: vec![(None, initial_game_state, None)],
             active_state_index: 0,
             worker,
+            opening_book: OpeningBook::load(DEFAULT_BOOK_PATH).unwrap_or_default(),
+            export_status: None,
+            position_text: String::new(),
+            position_error: None,
+            save_status: None,
+            net_address: DEFAULT_ADDRESS.to_string(),
+            net: None,
+            net_status: None,
+        };
+        app.update_ponder_states();
+        app
+    }
+
+    /// Returns the current settings to persist (see `Settings`).
+    #[must_use]
+    fn settings(&self) -> Settings {
+        Settings {
+            debug: self.debug,
+            flip_board: self.flip_board,
+            mark_extra_turns: self.mark_extra_turns,
+            difficulty: self.difficulty,
+            temperature: self.temperature,
+            deterministic: self.deterministic,
+            rules: self.rules,
+            pie_rule: self.pie_rule,
+            random_start: self.random_start,
+            stones_per_hole: self.stones_per_hole,
+            bot_think_time: self.bot_think_time,
+            ponder_replies: self.ponder_replies,
+            pause_when_unfocused: self.pause_when_unfocused,
+            tablebase_stones: self.tablebase_stones,
+            cache_size_limit: self.worker.cache_size_limit(),
+            use_rave: self.worker.use_rave(),
+            discount: self.worker.discount(),
+            exploration_rate: self.worker.exploration_rate(),
+            update_hz: self.worker.update_hz(),
         }
     }
 
@@ -72,18 +424,424 @@ impl MancalaApp {
 
     /// Returns the active `GameState`.
     fn active_state(&mut self) -> &mut GameState {
-        &mut self.history[self.active_state_index]
+        &mut self.history[self.active_state_index].1
+    }
+
+    /// Replaces the worker with a fresh one (with an empty cache), keeping its settings.
+    fn restart_worker(&mut self, ctx: &egui::Context) {
+        let worker = Worker::spawn(ctx, self.worker.cache_size_limit());
+        worker.set_use_rave(self.worker.use_rave());
+        worker.set_discount(self.worker.discount());
+        worker.set_exploration_rate(self.worker.exploration_rate());
+        if self.tablebase_stones != DEFAULT_MAX_STONES {
+            worker.set_tablebase_stones(self.tablebase_stones);
+        }
+        worker.set_rollout_budget(self.worker.rollout_budget());
+        worker.set_update_hz(self.worker.update_hz());
+        worker.set_line_plies(self.worker.line_plies());
+        worker.set_paused(self.worker.is_paused());
+        worker.set_active_state(self.active_state().clone());
+        self.worker = worker;
+        self.update_ponder_states();
+    }
+
+    /// Discards the history and starts a new game.
+    fn new_game(&mut self) {
+        if self.analysis.is_some() {
+            self.stop_analysis();
+        }
+        let mut initial_game_state =
+            GameState::new_with(self.rules, self.pie_rule, self.stones_per_hole)
+                .expect("the stone count should be in range");
+        if self.random_start {
+            let stones_per_side = HOLES_PER_SIDE as u8 * self.stones_per_hole;
+            let random_state = GameState::random_start(&mut thread_rng(), stones_per_side);
+            initial_game_state.p1_state = random_state.p1_state;
+            initial_game_state.p2_state = random_state.p2_state;
+        }
+        self.history = vec![(None, initial_game_state.clone(), None)];
+        self.active_state_index = 0;
+        self.show_hint = false;
+        self.bot_turn_start = None;
+        self.worker.set_active_state(initial_game_state);
+        self.update_ponder_states();
+    }
+
+    /// Makes the given state the active state, following the current one in the history.
+    /// `last_move` is the move that led to it from the current state, if any.
+    fn push_state(&mut self, last_move: Option<usize>, game_state: GameState) {
+        if self.analysis.is_some() {
+            self.stop_analysis();
+        }
+        let evaluation = self.active_evaluation();
+        self.history.truncate(self.active_state_index + 1);
+        self.history[self.active_state_index].2 = evaluation;
+        self.history.push((last_move, game_state, None));
+        self.active_state_index += 1;
+        self.bot_turn_start = None;
+        let active_state = self.active_state().clone();
+        self.worker.set_active_state(active_state);
+        self.update_ponder_states();
+    }
+
+    /// Returns the worker's current evaluation of the active state for Player 1, if it
+    /// has searched it.
+    #[must_use]
+    fn active_evaluation(&self) -> Option<f32> {
+        let game_state = &self.history[self.active_state_index].1;
+        self.worker
+            .state_data()
+            .filter(|data| &data.game_state == game_state)
+            .map(|data| p1_evaluation(game_state, &data.stats))
+    }
+
+    /// Parses a position in `GameState::to_notation` form and makes it the active
+    /// state, or records why it couldn't be loaded.
+    fn load_position(&mut self, notation: &str) {
+        if self.net.is_some() {
+            self.position_error = Some("Disconnect from the network game first".to_string());
+            return;
+        }
+        match GameState::from_notation(notation) {
+            Ok(game_state) => {
+                self.position_error = None;
+                self.push_state(None, game_state);
+            }
+            Err(err) => self.position_error = Some(format!("Invalid position: {err}")),
+        }
+    }
+
+    /// Returns whether undo and redo are allowed (they aren't in a network game or
+    /// when spectating).
+    #[must_use]
+    fn can_navigate_history(&self) -> bool {
+        self.net.is_none() && !self.spectate
+    }
+
+    /// Makes a different state in the history active, without truncating the history
+    /// (which happens when a move is made from it; see `push_state`).
+    fn set_active_state_index(&mut self, index: usize, ctx: &egui::Context) {
+        if self.analysis.is_some() {
+            self.stop_analysis();
+        }
+        self.active_state_index = index;
+        self.show_hint = false;
+        self.bot_turn_start = None;
+        let active_state = self.active_state().clone();
+        self.worker.set_active_state(active_state);
+        self.update_ponder_states();
+        ctx.clear_animations();
+    }
+
+    /// Steps back to the previous state in the history, if there is one.
+    fn undo(&mut self, ctx: &egui::Context) {
+        if self.active_state_index > 0 {
+            self.set_active_state_index(self.active_state_index - 1, ctx);
+        }
+    }
+
+    /// Steps forward to the next state in the history, if there is one.
+    fn redo(&mut self, ctx: &egui::Context) {
+        if self.active_state_index + 1 < self.history.len() {
+            self.set_active_state_index(self.active_state_index + 1, ctx);
+        }
+    }
+
+    /// Undoes on Ctrl+Z and redoes on Ctrl+Shift+Z, unless a text field has focus.
+    fn handle_undo_keys(&mut self, ctx: &egui::Context) {
+        if ctx.memory().focus().is_some() || !self.can_navigate_history() {
+            return;
+        }
+        let (undo_pressed, redo_pressed) = {
+            let input = ctx.input();
+            let z_pressed = input.modifiers.command && input.key_pressed(Key::Z);
+            (z_pressed && !input.modifiers.shift, z_pressed && input.modifiers.shift)
+        };
+        if undo_pressed {
+            self.undo(ctx);
+        } else if redo_pressed {
+            self.redo(ctx);
+        }
+    }
+
+    /// Writes the history and active state index to `SAVED_GAME_PATH` as JSON.
+    fn save_game(&self) -> io::Result<()> {
+        let saved_game = SavedGame {
+            history: self.history.clone(),
+            active_state_index: self.active_state_index,
+        };
+        let file = std::fs::File::create(SAVED_GAME_PATH)?;
+        serde_json::to_writer_pretty(io::BufWriter::new(file), &saved_game)?;
+        Ok(())
+    }
+
+    /// Replaces the history with the game saved in `SAVED_GAME_PATH`, returning a
+    /// description of the problem if it can't be read or isn't a valid game.
+    fn load_game(&mut self) -> Result<(), String> {
+        if self.net.is_some() {
+            return Err("Disconnect from the network game first".to_string());
+        }
+        let file = std::fs::File::open(SAVED_GAME_PATH).map_err(|err| err.to_string())?;
+        let saved_game: SavedGame =
+            serde_json::from_reader(io::BufReader::new(file)).map_err(|err| err.to_string())?;
+        if saved_game.active_state_index >= saved_game.history.len() {
+            return Err("the active state is missing from the history".to_string());
+        }
+        if let Some((_, game_state, _)) = saved_game
+            .history
+            .iter()
+            .find(|(_, game_state, _)| game_state.total_stones() > TOTAL_STONES)
+        {
+            return Err(format!(
+                "a position has {} stones, but at most {TOTAL_STONES} are allowed",
+                game_state.total_stones(),
+            ));
+        }
+
+        if self.analysis.is_some() {
+            self.stop_analysis();
+        }
+        self.history = saved_game.history;
+        self.active_state_index = saved_game.active_state_index;
+        self.show_hint = false;
+        self.bot_turn_start = None;
+        let active_state = self.active_state().clone();
+        self.worker.set_active_state(active_state);
+        self.update_ponder_states();
+        Ok(())
+    }
+
+    /// Copies the active position to the clipboard on Ctrl+C, and loads a position
+    /// from the clipboard on Ctrl+V, unless a text field has focus.
+    fn handle_clipboard_events(&mut self, ctx: &egui::Context) {
+        if ctx.memory().focus().is_some() {
+            return;
+        }
+        let events = ctx.input().events.clone();
+        for event in events {
+            match event {
+                Event::Copy => ctx.output().copied_text = self.active_state().to_notation(),
+                Event::Paste(text) if !self.spectate => self.load_position(&text),
+                _ => {}
+            }
+        }
+    }
+
+    /// Applies the messages received from the opponent in a network game. A move
+    /// that isn't legal or doesn't reach the opponent's state ends the connection, as
+    /// does a starting position sent to the host, sent twice, or that doesn't match.
+    fn handle_net_messages(&mut self) {
+        while let Some(message) = self.net.as_ref().and_then(NetConnection::try_recv) {
+            match message {
+                NetMessage::Start(_) if !self.net.as_ref().unwrap().expects_start() => {
+                    let problem = "The opponent sent an unexpected starting position";
+                    self.net.as_ref().unwrap().set_error(problem.to_string());
+                    break;
+                }
+                NetMessage::Start(game_state) => {
+                    let net = self.net.as_mut().unwrap();
+                    let local_state = &self.history[self.active_state_index].1;
+                    let swap_option_is_valid = match game_state.swap_option {
+                        SwapOption::Unavailable => true,
+                        SwapOption::Pending => game_state.cur_player == Player::Player1,
+                        SwapOption::Available => game_state.cur_player == Player::Player2,
+                    };
+                    let problem = if game_state.rules != local_state.rules {
+                        Some("The opponent's game uses different rules".to_string())
+                    } else if game_state.total_stones() != local_state.total_stones() {
+                        Some(format!(
+                            "The opponent's game has {} stones, but this one has {}",
+                            game_state.total_stones(),
+                            local_state.total_stones(),
+                        ))
+                    } else if !swap_option_is_valid {
+                        Some("The opponent's game has an invalid pie rule state".to_string())
+                    } else {
+                        None
+                    };
+                    if let Some(problem) = problem {
+                        net.set_error(problem);
+                        break;
+                    }
+                    net.accept_start();
+                    self.push_state(None, game_state);
+                }
+                NetMessage::Move { hole, resulting_state } => {
+                    let net = self.net.as_ref().unwrap();
+                    let mut game_state = self.history[self.active_state_index].1.clone();
+                    let is_valid = !net.expects_start()
+                        && game_state.cur_player != net.local_player()
+                        && game_state.try_make_move(hole).is_ok()
+                        && game_state == resulting_state;
+                    if !is_valid {
+                        net.set_error(format!("The opponent made an invalid move (hole {hole})"));
+                        break;
+                    }
+                    self.push_state(Some(hole), game_state);
+                }
+            }
+        }
+    }
+
+    /// Starts analyzing the active state, until it has `self.analysis_target` rollouts.
+    fn start_analysis(&mut self) {
+        self.analysis = Some(self.analysis_target);
+        self.worker.set_rollout_budget(self.analysis);
+        self.update_ponder_states();
+    }
+
+    /// Ends the analysis in progress, restoring the normal search settings.
+    fn stop_analysis(&mut self) {
+        self.analysis = None;
+        self.worker.set_rollout_budget(self.difficulty.settings().0);
+        self.update_ponder_states();
+    }
+
+    /// Shows a window with the position after the previewed move (see `self.peek`),
+    /// annotated with the worker's stats for it, without changing the active state.
+    fn show_peek_window(&mut self, ctx: &egui::Context) {
+        let active_state = &self.history[self.active_state_index].1;
+        let peek = self.peek.clone().filter(|(game_state, hole)| {
+            game_state == active_state && game_state.is_legal_move(*hole)
+        });
+        let (game_state, hole) = match peek {
+            Some(peek) => peek,
+            None => {
+                self.peek = None;
+                return;
+            }
+        };
+
+        let next_state = game_state.after_move(hole);
+        let option_index = game_state.valid_moves().position(|option| option == hole);
+        let next_stats = self
+            .worker
+            .state_data()
+            .filter(|data| data.game_state == game_state)
+            .and_then(|data| data.child_stats.get(option_index?).cloned().flatten())
+            // the child stats are only published once the window is open, and not while
+            // the worker is idle, so ask for them directly until then
+            .or_else(|| self.worker.stats_for(&next_state));
+        let exploration_rate = self.worker.exploration_rate();
+
+        let mut is_open = true;
+        egui::Window::new(format!("Preview: {}", describe_move(hole)))
+            .id(egui::Id::new("peek_window"))
+            .open(&mut is_open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.heading(game_heading(ui, &next_state));
+                match &next_stats {
+                    Some(stats) => ui.label(format!(
+                        "{} to move ({} rollouts)",
+                        next_state.cur_player,
+                        stats.num_rollouts.to_formatted_string(&Locale::en),
+                    )),
+                    None => ui.label("Not explored yet"),
+                };
+                add_annotated_game_state(
+                    ui,
+                    &next_state,
+                    next_stats.as_ref(),
+                    &[],
+                    exploration_rate,
+                    self.flip_board,
+                    self.mark_extra_turns,
+                    true,
+                    None,
+                    |_| {},
+                );
+            });
+        if !is_open {
+            self.peek = None;
+        }
+    }
+
+    /// Sends the worker the states to ponder alongside the active state.
+    fn update_ponder_states(&mut self) {
+        let ponder_states = if self.ponder_replies && self.analysis.is_none() {
+            self.active_state()
+                .successors()
+                .map(|(_, game_state)| game_state)
+                .collect()
+        } else {
+            Vec::new()
+        };
+        self.worker.set_ponder_states(ponder_states);
     }
 }
 
 impl eframe::App for MancalaApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, THEME_KEY, &self.theme);
+        eframe::set_value(storage, SETTINGS_KEY, &self.settings());
+    }
+
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        self.handle_net_messages();
+        self.handle_clipboard_events(ctx);
+        self.handle_undo_keys(ctx);
+
+        let has_focus = ctx.input().raw.has_focus;
+        let paused = self.pause_when_unfocused && !has_focus && self.bot_turn_start.is_none();
+        if paused != self.worker.is_paused() {
+            self.worker.set_paused(paused);
+        }
+
         SidePanel::left("side_panel").show(ctx, |ui| {
             egui::warn_if_debug_build(ui);
             ui.heading("Settings");
 
-            ui.checkbox(&mut self.debug, "Debug");
+            if let Some(error) = self.worker.error() {
+                let error_color = ui.visuals().error_fg_color;
+                ui.colored_label(error_color, format!("The worker crashed: {error}"));
+                if ui.button("Restart worker").clicked() {
+                    self.restart_worker(ctx);
+                }
+                ui.separator();
+            }
+
+            if ui
+                .checkbox(&mut self.debug, "Debug")
+                .on_hover_text("Also logs the search's progress to stderr")
+                .changed()
+            {
+                logging::set_verbose(self.debug);
+            }
             ctx.set_debug_on_hover(self.debug);
+            if self.debug {
+                ui.label(format!("Truncated rollouts: {}", num_truncated_rollouts()));
+                let active_state = &self.history[self.active_state_index].1;
+                if self.worker.active_state().as_ref() != Some(active_state) {
+                    ui.label("The worker is searching a different position");
+                    if ui.button("Resync worker").clicked() {
+                        let active_state = self.active_state().clone();
+                        self.worker.set_active_state(active_state);
+                    }
+                }
+            }
+
+            let old_theme = self.theme;
+            ComboBox::from_label("Theme")
+                .selected_text(format!("{:?}", self.theme))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.theme, ThemePreference::System, "System");
+                    ui.selectable_value(&mut self.theme, ThemePreference::Light, "Light");
+                    ui.selectable_value(&mut self.theme, ThemePreference::Dark, "Dark");
+                });
+            if self.theme != old_theme {
+                ctx.set_visuals(self.theme.visuals(frame.info().system_theme));
+            }
+
+            ui.checkbox(&mut self.flip_board, "Flip board");
+            ui.checkbox(&mut self.mark_extra_turns, "Mark extra-turn moves")
+                .on_hover_text("Outline the holes whose last stone would land in your store");
+            ui.checkbox(&mut self.spectate, "Spectator mode")
+                .on_hover_text("Only display the game, without playing moves");
+
+            ui.checkbox(&mut self.show_tree, "Show search tree");
+            ui.checkbox(&mut self.show_history, "Show move history");
+            ui.checkbox(&mut self.show_eval_graph, "Show evaluation graph");
 
             ui.separator();
 
@@ -107,30 +865,514 @@ impl eframe::App for MancalaApp {
                 self.worker.clear_cache();
             }
 
+            ui.checkbox(&mut self.pause_when_unfocused, "Pause when unfocused")
+                .on_hover_text("Stop searching while the window is in the background");
+
+            ui.label("Worker update rate:");
+            let mut update_hz = self.worker.update_hz();
+            let slider = Slider::new(&mut update_hz, 5.0..=120.0).suffix(" Hz");
+            if ui.add(slider).changed() {
+                self.worker.set_update_hz(update_hz);
+            }
+
+            ui.separator();
+
+            ui.label("Rules for new games:");
+            ComboBox::from_label("Ruleset")
+                .selected_text(format!("{:?}", self.rules.ruleset))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.rules.ruleset, Ruleset::Kalah, "Kalah");
+                    ui.selectable_value(&mut self.rules.ruleset, Ruleset::Oware, "Oware");
+                    ui.selectable_value(&mut self.rules.ruleset, Ruleset::Relay, "Relay");
+                });
+            let has_kalah_options = self.rules.ruleset != Ruleset::Oware;
+            ui.add(
+                Slider::new(&mut self.stones_per_hole, 1..=MAX_STONES_PER_HOLE)
+                    .text("stones per hole"),
+            );
+            ui.checkbox(&mut self.pie_rule, "Pie rule");
+            ui.checkbox(&mut self.random_start, "Random start")
+                .on_hover_text("Scatter the stones randomly (the same way on both sides)");
+            ui.add_enabled_ui(has_kalah_options, |ui| {
+                let mut sow_into_both = self.rules.store_rule == StoreRule::SowIntoBoth;
+                if ui
+                    .checkbox(&mut sow_into_both, "Sow into both stores")
+                    .changed()
+                {
+                    self.rules.store_rule = if sow_into_both {
+                        StoreRule::SowIntoBoth
+                    } else {
+                        StoreRule::SkipOpponents
+                    };
+                }
+                ComboBox::from_label("Captures")
+                    .selected_text(match self.rules.capture_rule {
+                        CaptureRule::Standard => "Standard",
+                        CaptureRule::NoCaptures => "None",
+                        CaptureRule::EvenIfOppositeEmpty => "Even if opposite empty",
+                    })
+                    .show_ui(ui, |ui| {
+                        let capture_rule = &mut self.rules.capture_rule;
+                        ui.selectable_value(capture_rule, CaptureRule::Standard, "Standard");
+                        ui.selectable_value(capture_rule, CaptureRule::NoCaptures, "None");
+                        ui.selectable_value(
+                            capture_rule,
+                            CaptureRule::EvenIfOppositeEmpty,
+                            "Even if opposite empty",
+                        );
+                    });
+            });
+            let mut pass_turn = self.rules.empty_side_rule == EmptySideRule::PassTurn;
+            if ui
+                .checkbox(&mut pass_turn, "Pass when a side is empty")
+                .on_hover_text("Otherwise, the game ends when either side is empty")
+                .changed()
+            {
+                self.rules.empty_side_rule = if pass_turn {
+                    EmptySideRule::PassTurn
+                } else {
+                    EmptySideRule::EndGame
+                };
+            }
+            let new_game_button = Button::new("New game");
+            if ui
+                .add_enabled(self.net.is_none(), new_game_button)
+                .on_disabled_hover_text("Disconnect from the network game first")
+                .clicked()
+            {
+                self.new_game();
+            }
+
+            for player in [Player::Player1, Player::Player2] {
+                let controller = match player {
+                    Player::Player1 => &mut self.player1_controller,
+                    Player::Player2 => &mut self.player2_controller,
+                };
+                ComboBox::new(("controller", player), player.to_string())
+                    .selected_text(format!("{controller:?}"))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(controller, Controller::Human, "Human");
+                        ui.selectable_value(controller, Controller::Bot, "Bot");
+                    });
+            }
+
+            ui.label("Bot think time:");
+            ui.add(Slider::new(&mut self.bot_think_time, 0.1..=10.0).suffix(" s"));
+
+            let old_difficulty = self.difficulty;
+            ComboBox::from_label("Difficulty")
+                .selected_text(format!("{:?}", self.difficulty))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.difficulty, Difficulty::Easy, "Easy");
+                    ui.selectable_value(&mut self.difficulty, Difficulty::Medium, "Medium");
+                    ui.selectable_value(&mut self.difficulty, Difficulty::Hard, "Hard");
+                });
+            if self.difficulty != old_difficulty {
+                let (rollout_budget, temperature) = self.difficulty.settings();
+                if self.analysis.is_none() {
+                    self.worker.set_rollout_budget(rollout_budget);
+                }
+                self.temperature = temperature;
+            }
+
+            ui.label("Move selection temperature:");
+            ui.add_enabled(!self.deterministic, Slider::new(&mut self.temperature, 0.0..=2.0));
+            ui.checkbox(&mut self.deterministic, "Deterministic move selection")
+                .on_hover_text("Break ties by expected score, then by hole");
+
+            let mut use_rave = self.worker.use_rave();
+            if ui.checkbox(&mut use_rave, "Use RAVE").changed() {
+                self.worker.set_use_rave(use_rave);
+            }
+
+            ui.label("Solve exactly with at most:");
+            let slider = Slider::new(&mut self.tablebase_stones, 0..=PRACTICAL_MAX_STONES)
+                .suffix(" stones in holes");
+            let response = ui.add(slider);
+            // rebuilding is expensive, so wait until the slider is let go
+            if response.drag_released() || (response.changed() && !response.dragged()) {
+                self.worker.set_tablebase_stones(self.tablebase_stones);
+            }
+            if self.worker.tablebase_stones() != self.tablebase_stones {
+                ui.label("Building the tablebase...");
+            }
+
+            ui.label("Exploration rate:");
+            let mut exploration_rate = self.worker.exploration_rate();
+            let slider = Slider::new(&mut exploration_rate, 1.0..=1000.0).logarithmic(true);
+            if ui.add(slider).changed() {
+                self.worker.set_exploration_rate(exploration_rate);
+            }
+
+            let mut discount_games = self.worker.discount() < 1.0;
+            if ui
+                .checkbox(&mut discount_games, "Prefer faster wins")
+                .on_hover_text("Discount rollout scores by the length of the game")
+                .changed()
+            {
+                let discount = if discount_games {
+                    DEFAULT_DISCOUNT
+                } else {
+                    1.0
+                };
+                self.worker.set_discount(discount);
+            }
+            if discount_games {
+                let mut discount = self.worker.discount();
+                let slider = Slider::new(&mut discount, 0.9..=0.999).text("discount per ply");
+                if ui.add(slider).changed() {
+                    self.worker.set_discount(discount);
+                }
+            }
+
+            if ui
+                .checkbox(&mut self.ponder_replies, "Ponder replies")
+                .changed()
+            {
+                self.update_ponder_states();
+            }
+            if self.ponder_replies {
+                let valid_moves = self.active_state().valid_moves().collect_vec();
+                let sample_counts = self.worker.ponder_sample_counts();
+                for (hole_index, count) in valid_moves.into_iter().zip(sample_counts) {
+                    ui.label(format!(
+                        "After {}: {} samples",
+                        describe_move(hole_index),
+                        count.to_formatted_string(&Locale::en),
+                    ));
+                }
+            }
+
             ui.separator();
 
             let sps = self.worker.samples_per_second().round() as u64;
             ui.label(format!("{} samples/sec", sps.to_formatted_string(&Locale::en)));
+            add_sps_plot(ui, &self.worker.sps_history());
 
             ui.label(format!("Average search depth: {:.1}", self.worker.average_search_depth()));
+            ui.label(format!("Max search depth: {}", self.worker.max_search_depth()));
+
+            let nps = self.worker.new_nodes_per_second().round() as u64;
+            ui.label(format!("{} new nodes/sec", nps.to_formatted_string(&Locale::en)));
+
+            ui.separator();
+
+            ui.label("Analysis rollout target:");
+            let slider = Slider::new(&mut self.analysis_target, 100_000..=50_000_000)
+                .clamp_to_range(false)
+                .logarithmic(true);
+            ui.add_enabled(self.analysis.is_none(), slider);
+            match self.analysis {
+                None => {
+                    let is_game_over = self.active_state().result().is_some();
+                    if ui
+                        .add_enabled(!is_game_over, Button::new("Analyze"))
+                        .clicked()
+                    {
+                        self.start_analysis();
+                    }
+                }
+                Some(target) => {
+                    let (num_rollouts, is_solved) = self
+                        .worker
+                        .state_data()
+                        .filter(|data| &data.game_state == self.active_state())
+                        .map_or((0, false), |data| {
+                            (data.stats.num_rollouts, data.stats.proven.is_some())
+                        });
+                    ui.label(format!(
+                        "{} / {} rollouts",
+                        num_rollouts.to_formatted_string(&Locale::en),
+                        target.to_formatted_string(&Locale::en),
+                    ));
+                    ui.add(value_bar(num_rollouts.min(target), target, Direction::LeftToRight));
+                    let is_complete = num_rollouts >= target || is_solved;
+                    if is_complete {
+                        ui.label("Analysis complete");
+                    }
+                    if ui
+                        .button(if is_complete { "Done" } else { "Cancel" })
+                        .clicked()
+                    {
+                        self.stop_analysis();
+                    }
+                }
+            }
+
+            ui.separator();
+
+            let state_data = self
+                .worker
+                .state_data()
+                .filter(|data| &data.game_state == self.active_state());
+            let export_button = Button::new("Export analysis");
+            let export_response = ui
+                .add_enabled(state_data.is_some(), export_button)
+                .on_disabled_hover_text("No analysis for this position yet");
+            if export_response.clicked() {
+                let data = state_data.unwrap();
+                let result = std::fs::File::create(DEFAULT_ANALYSIS_PATH).and_then(|file| {
+                    write_analysis_csv(io::BufWriter::new(file), &data.game_state, &data.stats)
+                });
+                self.export_status = Some(match result {
+                    Ok(()) => format!("Exported to {DEFAULT_ANALYSIS_PATH}"),
+                    Err(err) => format!("Export failed: {err}"),
+                });
+            }
+            if let Some(export_status) = &self.export_status {
+                ui.label(export_status.as_str());
+            }
+
+            ui.separator();
+
+            ui.label("Position (Ctrl+C/Ctrl+V to copy/paste):");
+            if ui.button("Copy position").clicked() {
+                ui.output().copied_text = self.active_state().to_notation();
+            }
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut self.position_text);
+                if ui.button("Load").clicked() {
+                    let notation = self.position_text.clone();
+                    self.load_position(&notation);
+                }
+            });
+            if let Some(error) = &self.position_error {
+                let error_color = ui.visuals().error_fg_color;
+                ui.colored_label(error_color, error.as_str());
+            }
+
+            ui.horizontal(|ui| {
+                if ui.button("Save game").clicked() {
+                    self.save_status = Some(match self.save_game() {
+                        Ok(()) => format!("Saved to {SAVED_GAME_PATH}"),
+                        Err(err) => format!("Save failed: {err}"),
+                    });
+                }
+                if ui.button("Load game").clicked() {
+                    self.save_status = Some(match self.load_game() {
+                        Ok(()) => format!("Loaded {SAVED_GAME_PATH}"),
+                        Err(err) => format!("Load failed: {err}"),
+                    });
+                }
+            });
+            if let Some(save_status) = &self.save_status {
+                ui.label(save_status.as_str());
+            }
+
+            ui.separator();
+
+            ui.label("Network game address:");
+            ui.text_edit_singleline(&mut self.net_address);
+            let error_color = ui.visuals().error_fg_color;
+            if let Some(net) = &self.net {
+                if let Some(error) = net.error() {
+                    ui.colored_label(error_color, error);
+                } else if net.is_connected() {
+                    ui.label(format!("Connected; you play {}", net.local_player()));
+                } else {
+                    ui.label("Waiting for the opponent...");
+                }
+                if ui.button("Disconnect").clicked() {
+                    self.net = None;
+                }
+            } else {
+                ui.horizontal(|ui| {
+                    if ui.button("Host game").clicked() {
+                        let game_state = self.history[self.active_state_index].1.clone();
+                        match NetConnection::host(&self.net_address, game_state, ctx) {
+                            Ok(net) => {
+                                self.net = Some(net);
+                                self.net_status = None;
+                            }
+                            Err(err) => self.net_status = Some(format!("Failed to host: {err}")),
+                        }
+                    }
+                    if ui.button("Join game").clicked() {
+                        self.net = Some(NetConnection::join(&self.net_address, ctx));
+                        self.net_status = None;
+                    }
+                });
+                if let Some(net_status) = &self.net_status {
+                    ui.colored_label(error_color, net_status.as_str());
+                }
+            }
         });
 
+        if self.show_tree {
+            SidePanel::right("tree_panel").show(ctx, |ui| {
+                ui.heading("Search Tree");
+
+                let slider = Slider::new(&mut self.tree_plies, 1..=12).text("plies");
+                if ui.add(slider).changed() {
+                    self.worker
+                        .set_line_plies(self.tree_plies.max(BEST_LINE_PLIES));
+                }
+                ui.label("Scores are for the player choosing the move.");
+                ui.separator();
+
+                let state_data = self
+                    .worker
+                    .state_data()
+                    .filter(|data| &data.game_state == self.active_state());
+                egui::ScrollArea::vertical().show(ui, |ui| match state_data {
+                    Some(data) => {
+                        let line = &data.line[..data.line.len().min(self.tree_plies)];
+                        add_search_tree(ui, &data.game_state, &data.stats, line);
+                    }
+                    None => {
+                        ui.label("No search results yet");
+                    }
+                });
+            });
+        }
+
+        if self.show_history {
+            SidePanel::right("history_panel").show(ctx, |ui| {
+                ui.heading("Moves");
+                ui.separator();
+
+                let mut clicked_index = None;
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for (index, entry) in describe_history(&self.history).into_iter().enumerate() {
+                        let is_active = index == self.active_state_index;
+                        if ui.selectable_label(is_active, entry).clicked() {
+                            clicked_index = Some(index);
+                        }
+                    }
+                });
+                if let Some(index) = clicked_index.filter(|_| self.can_navigate_history()) {
+                    self.set_active_state_index(index, ctx);
+                }
+            });
+        }
+
+        if self.show_eval_graph {
+            TopBottomPanel::bottom("eval_panel").show(ctx, |ui| {
+                let mut evaluations = self.history.iter().map(|entry| entry.2).collect_vec();
+                if let Some(evaluation) = self.active_evaluation() {
+                    evaluations[self.active_state_index] = Some(evaluation);
+                }
+                let clicked_index = add_eval_plot(ui, &evaluations, self.active_state_index);
+                if let Some(index) = clicked_index.filter(|_| self.can_navigate_history()) {
+                    self.set_active_state_index(index, ctx);
+                }
+            });
+        }
+
         let frame = Frame::central_panel(&ctx.style()).inner_margin(10.0);
         CentralPanel::default().frame(frame).show(ctx, |ui| {
-            ui.heading("Current Game State");
+            ui.heading(game_heading(ui, self.active_state()));
+
+            if self.can_navigate_history() {
+                ui.horizontal(|ui| {
+                    let can_undo = self.active_state_index > 0;
+                    let can_redo = self.active_state_index + 1 < self.history.len();
+                    if ui
+                        .add_enabled(can_undo, Button::new("Undo"))
+                        .on_hover_text("Ctrl+Z")
+                        .clicked()
+                    {
+                        self.undo(ui.ctx());
+                    }
+                    if ui
+                        .add_enabled(can_redo, Button::new("Redo"))
+                        .on_hover_text("Ctrl+Shift+Z")
+                        .clicked()
+                    {
+                        self.redo(ui.ctx());
+                    }
+                });
+            }
 
-            let state_stats = self
+            let state_data = self
                 .worker
                 .state_data()
-                .filter(|data| &data.game_state == self.active_state())
-                .map(|data| data.stats);
-            let game_state = self.active_state();
+                .filter(|data| &data.game_state == self.active_state());
+            let state_stats = state_data.as_ref().map(|data| &data.stats);
+            let score_history = state_data
+                .as_ref()
+                .map_or(&[][..], |data| &data.score_history[..]);
+            let best_line = state_data.as_ref().map_or(&[][..], |data| &data.line);
+            let book_move = self
+                .opening_book
+                .get(&self.history[self.active_state_index].1);
+            let flipped = self.flip_board;
+            let mark_extra_turns = self.mark_extra_turns;
+            let read_only = self.spectate;
+            let temperature = self.temperature;
+            let deterministic = self.deterministic;
+            let exploration_rate = self.worker.exploration_rate();
+            let player1_controller = self.player1_controller;
+            let player2_controller = self.player2_controller;
+            let net_player = self
+                .net
+                .as_ref()
+                .map(|net| (net.local_player(), net.is_ready()));
+            let show_hint = &mut self.show_hint;
+            let game_state = &self.history[self.active_state_index].1;
+
+            // the bot moves whenever it's the turn of a player it controls (including
+            // after earning an extra turn)
+            let cur_controller = match game_state.cur_player {
+                Player::Player1 => player1_controller,
+                Player::Player2 => player2_controller,
+            };
+            let is_bot_turn = game_state.result().is_none()
+                && net_player.is_none()
+                && cur_controller == Controller::Bot;
+
+            // in a network game, the human only moves for their own player once play starts
+            let is_waiting_for_opponent = game_state.result().is_none()
+                && net_player
+                    .is_some_and(|(player, is_ready)| !is_ready || player != game_state.cur_player);
 
             let mut move_to_make = None;
+            let mut peek_move = None;
+            let shift_held = ui.input().modifiers.shift;
 
-            add_annotated_game_state(ui, game_state, state_stats.as_ref(), |hole| {
-                move_to_make = Some(hole);
-            });
+            // the hint is the best (maximum visit count) choice
+            let hint_option = state_stats.map(StateStats::best_option);
+            let hint = hint_option
+                .filter(|_| *show_hint)
+                .and_then(|option_index| game_state.valid_moves().nth(option_index));
+
+            add_annotated_game_state(
+                ui,
+                game_state,
+                state_stats,
+                score_history,
+                exploration_rate,
+                flipped,
+                mark_extra_turns,
+                read_only,
+                hint,
+                |hole| {
+                    // shift-clicking previews the move instead of making it
+                    if shift_held {
+                        peek_move = Some(hole);
+                    } else {
+                        move_to_make = Some(hole);
+                    }
+                },
+            );
+            if let Some(hole) = peek_move {
+                self.peek = Some((game_state.clone(), hole));
+            }
+
+            // handle number key presses just like clicks on the corresponding holes (unless
+            // they're being typed into a text field)
+            let text_has_focus = ui.memory().focus().is_some();
+            if !read_only && !text_has_focus {
+                let input = ui.input();
+                if let Some((hole_index, _)) = HOLE_KEYS
+                    .iter()
+                    .take(HOLES_PER_SIDE)
+                    .enumerate()
+                    .find(|(i, key)| game_state.is_legal_move(*i) && input.key_pressed(**key))
+                {
+                    move_to_make = Some(hole_index);
+                }
+            }
 
             if let Some(final_score) = game_state.result() {
                 // the game is over; display the final score information
@@ -149,56 +1391,341 @@ impl eframe::App for MancalaApp {
                     add_player_score(0, Player::Player1, p1_score, final_score);
                     add_player_score(1, Player::Player2, p2_score, -final_score);
                 });
-            } else {
+            } else if is_bot_turn {
+                ui.vertical_centered(|ui| {
+                    ui.label("The bot is thinking...");
+                });
+            } else if is_waiting_for_opponent {
+                ui.vertical_centered(|ui| {
+                    ui.label("Waiting for the opponent...");
+                });
+            } else if !read_only {
                 // the game is not over; show helper buttons to make moves
                 ui.vertical_centered(|ui| {
                     if ui.button("Random move").clicked() {
-                        move_to_make = game_state
-                            .player(game_state.cur_player)
-                            .non_empty_holes()
-                            .choose(&mut rand::thread_rng());
+                        move_to_make = game_state.valid_moves().choose(&mut rand::thread_rng());
+                    }
+
+                    let hint_button = Button::new("Hint");
+                    if ui.add_enabled(hint_option.is_some(), hint_button).clicked() {
+                        *show_hint = !*show_hint;
+                    }
+                    if let (Some(hint), Some(option_index)) = (hint, hint_option) {
+                        let option_stats = &state_stats.unwrap().options[option_index];
+                        ui.label(format!(
+                            "Hint: {} ({:+.1} ± {:.1})",
+                            describe_move(hint),
+                            option_stats.expected_score(),
+                            option_stats.score_std_error(),
+                        ));
+                        ui.label(explain_move(game_state, hint, Some(option_stats)));
                     }
 
                     let single_valid_move = game_state.valid_moves().exactly_one().ok();
-                    let enable_mcts_button = state_stats.is_some() || single_valid_move.is_some();
-
-                    let button = Button::new("Best move (by MCTS)");
-                    if ui.add_enabled(enable_mcts_button, button).clicked() {
-                        move_to_make = Some(single_valid_move.unwrap_or_else(|| {
-                            // pick a random best (maximum visit count) choice
-                            let index = get_best_options(&state_stats.unwrap().options)
-                                .choose(&mut thread_rng())
-                                .unwrap();
-                            game_state.valid_moves().nth(index).unwrap()
-                        }));
+                    let known_move = single_valid_move.or(book_move);
+                    let enable_mcts_button = state_stats.is_some() || known_move.is_some();
+
+                    let button = Button::new(if book_move.is_some() {
+                        "Best move (from book)"
+                    } else {
+                        "Best move (by MCTS)"
+                    });
+                    let best_move_key_pressed = !text_has_focus && {
+                        let input = ui.input();
+                        input.key_pressed(Key::Space) || input.key_pressed(Key::Enter)
+                    };
+                    if ui.add_enabled(enable_mcts_button, button).clicked()
+                        || (enable_mcts_button && best_move_key_pressed)
+                    {
+                        move_to_make = choose_move(
+                            game_state,
+                            state_stats,
+                            book_move,
+                            temperature,
+                            deterministic,
+                        );
+                    }
+
+                    if !best_line.is_empty() {
+                        let moves = best_line.iter().map(|step| match step.hole {
+                            SWAP_MOVE => "swap".to_string(),
+                            hole => (hole + 1).to_string(),
+                        });
+                        ui.label(format!("Best line: {}", moves.format(", ")))
+                            .on_hover_text("The most-visited moves, for both players");
                     }
                 });
             }
 
-            if let Some(hole_index) = move_to_make {
-                game_state.make_move(hole_index);
-                let active_state = game_state.clone();
-                self.worker.set_active_state(active_state);
+            if is_waiting_for_opponent {
+                move_to_make = None;
+            }
+
+            if is_bot_turn {
+                // ignore the human's input, and move once the think time has passed
+                let think_time = Duration::from_secs_f32(self.bot_think_time);
+                let turn_start = *self.bot_turn_start.get_or_insert_with(|| {
+                    self.worker.search_for(game_state.clone(), think_time);
+                    Instant::now()
+                });
+                let elapsed = turn_start.elapsed();
+                move_to_make = if elapsed >= think_time {
+                    choose_move(game_state, state_stats, book_move, temperature, deterministic)
+                } else {
+                    ui.ctx().request_repaint_after(think_time - elapsed);
+                    None
+                };
+            }
+
+            if let Some(hole_index) = move_to_make.filter(|&hole| game_state.is_legal_move(hole)) {
+                *show_hint = false;
+                let next_state = game_state.after_move(hole_index);
+                if let Some(net) = &self.net {
+                    net.send_move(hole_index, next_state.clone());
+                }
+                self.push_state(Some(hole_index), next_state);
                 ui.ctx().clear_animations();
             }
         });
+
+        self.show_peek_window(ctx);
+        self.worker.set_publish_child_stats(self.peek.is_some());
+    }
+}
+
+/// Returns the move to play in `game_state`: the only valid move or the opening book's
+/// move if there is one, and otherwise a move sampled from the search statistics at
+/// the given temperature (at temperature 0, a random best one), or the deterministic
+/// best move if `deterministic` is set. Returns None if the search hasn't produced
+/// any statistics yet.
+fn choose_move(
+    game_state: &GameState,
+    stats: Option<&StateStats>,
+    book_move: Option<usize>,
+    temperature: f32,
+    deterministic: bool,
+) -> Option<usize> {
+    let single_valid_move = game_state.valid_moves().exactly_one().ok();
+    single_valid_move.or(book_move).or_else(|| {
+        let stats = stats?;
+        let index = if deterministic {
+            stats.best_option()
+        } else {
+            sample_move_with_temperature(stats, temperature, &mut thread_rng())
+        };
+        game_state.valid_moves().nth(index)
+    })
+}
+
+/// Adds a small plot of the worker's recent sample rates, with the latest at x = 0.
+fn add_sps_plot(ui: &mut Ui, sps_history: &VecDeque<f32>) {
+    let num_readings = sps_history.len();
+    let points: PlotPoints = sps_history
+        .iter()
+        .enumerate()
+        .map(|(i, &sps)| [i as f64 + 1.0 - num_readings as f64, f64::from(sps)])
+        .collect();
+    Plot::new("sps_plot")
+        .height(60.0)
+        .allow_drag(false)
+        .allow_zoom(false)
+        .include_y(0.0)
+        .label_formatter(|_, point| format!("{:.0}s ago\n{:.0} samples/sec", -point.x, point.y))
+        .show(ui, |plot_ui| plot_ui.line(Line::new(points)));
+}
+
+/// Returns the expected score of the best option from a position, from Player 1's
+/// perspective.
+#[must_use]
+fn p1_evaluation(game_state: &GameState, stats: &StateStats) -> f32 {
+    let score = stats.options[stats.best_option()]
+        .expected_score()
+        .into_inner();
+    match game_state.cur_player {
+        Player::Player1 => score,
+        Player::Player2 => -score,
+    }
+}
+
+/// Adds a plot of the evaluation for Player 1 at each ply of the history (skipping
+/// plies without one), with the active ply highlighted. Returns the ply that was
+/// clicked, if any.
+fn add_eval_plot(ui: &mut Ui, evaluations: &[Option<f32>], active_index: usize) -> Option<usize> {
+    let points = evaluations
+        .iter()
+        .enumerate()
+        .filter_map(|(ply, evaluation)| Some([ply as f64, f64::from((*evaluation)?)]))
+        .collect_vec();
+    let active_point = points
+        .iter()
+        .find(|point| point[0] as usize == active_index)
+        .copied();
+    let highlight_color = ui.visuals().selection.bg_fill;
+    let max_ply = evaluations.len() - 1;
+    Plot::new("eval_plot")
+        .height(120.0)
+        .allow_drag(false)
+        .allow_zoom(false)
+        .allow_scroll(false)
+        .include_x(0.0)
+        .include_x(max_ply.max(1) as f64)
+        .include_y(-1.0)
+        .include_y(1.0)
+        .label_formatter(|_, point| format!("ply {:.0}\n{:+.1} for Player 1", point.x, point.y))
+        .show(ui, |plot_ui| {
+            plot_ui.line(Line::new(PlotPoints::from(points)));
+            plot_ui.vline(VLine::new(active_index as f64).color(highlight_color));
+            if let Some(point) = active_point {
+                let marker = Points::new(vec![point])
+                    .shape(MarkerShape::Circle)
+                    .radius(4.0)
+                    .color(highlight_color);
+                plot_ui.points(marker);
+            }
+            if plot_ui.plot_clicked() {
+                plot_ui
+                    .pointer_coordinate()
+                    .map(|pointer| (pointer.x.round().max(0.0) as usize).min(max_ply))
+            } else {
+                None
+            }
+        })
+        .inner
+}
+
+/// Adds an indented text view of a position's options (with their visit counts and
+/// expected scores), with the position after the option followed by `line` expanded
+/// beneath it, recursively.
+fn add_search_tree(ui: &mut Ui, game_state: &GameState, stats: &StateStats, line: &[LineStep]) {
+    ui.label(format!(
+        "{} to move ({} rollouts):",
+        game_state.cur_player,
+        stats.num_rollouts.to_formatted_string(&Locale::en),
+    ));
+    for (hole, option_stats) in game_state.valid_moves().zip_eq(&stats.options) {
+        let num_visits = option_stats.num_rollouts; // copy out of the packed struct
+        let text = format!(
+            "{} → ({} visits, {:+.1})",
+            describe_move(hole),
+            num_visits.to_formatted_string(&Locale::en),
+            option_stats.expected_score(),
+        );
+        match line.split_first().filter(|(step, _)| step.hole == hole) {
+            Some((step, rest)) => {
+                ui.strong(text);
+                ui.indent(hole, |ui| match (&step.stats, step.game_state.outcome()) {
+                    (Some(stats), _) => add_search_tree(ui, &step.game_state, stats, rest),
+                    (None, Some(outcome)) => {
+                        ui.label(format!("Game over: {outcome}"));
+                    }
+                    (None, None) => {
+                        ui.label("Not explored yet");
+                    }
+                });
+            }
+            None => {
+                ui.label(text);
+            }
+        }
+    }
+}
+
+/// Returns a description of a move for display, e.g. "hole 3" (numbering holes from 1).
+fn describe_move(hole: usize) -> String {
+    if hole == SWAP_MOVE {
+        "swapping sides".to_string()
+    } else {
+        format!("hole {}", hole + 1)
+    }
+}
+
+/// Returns a line describing each entry of a game history: the ply number, the player
+/// who moved, and their move.
+fn describe_history(history: &[(Option<usize>, GameState, Option<f32>)]) -> Vec<String> {
+    let mut entries = vec!["Start".to_string()];
+    for (ply, ((_, prev_state, _), (last_move, _, _))) in history.iter().tuple_windows().enumerate()
+    {
+        let ply = ply + 1;
+        entries.push(match *last_move {
+            Some(hole) => {
+                let mover = prev_state.cur_player;
+                let extra_turn = prev_state.move_effects(hole).extra_turn;
+                let suffix = if extra_turn { " (extra turn)" } else { "" };
+                format!("{ply}. {mover}: {}{suffix}", describe_move(hole))
+            }
+            None => format!("{ply}. (position set)"),
+        });
+    }
+    entries
+}
+
+/// Returns the heading for the central panel: a prominent result banner if the game
+/// is over, or a plain title otherwise.
+fn game_heading(ui: &Ui, game_state: &GameState) -> RichText {
+    match game_state.outcome() {
+        None => match game_state.is_decided() {
+            Some(margin) => {
+                let winner = if margin > 0 {
+                    Player::Player1
+                } else {
+                    Player::Player2
+                };
+                RichText::new(format!(
+                    "{winner} has won by at least {} (the game is decided)",
+                    margin.abs()
+                ))
+                .color(ui.visuals().selection.bg_fill)
+            }
+            None => RichText::new("Current Game State"),
+        },
+        Some(GameOutcome::Draw) => RichText::new("Game over: it's a draw!").strong(),
+        Some(outcome) => RichText::new(format!("Game over: {outcome}!"))
+            .strong()
+            .color(ui.visuals().selection.bg_fill),
     }
 }
 
 /// Adds a widget that displays the game state, annotated with extra information.
+/// Player 1's side is at the bottom, unless `flipped` is set. The current player's
+/// `hint` move (if any) is highlighted, and if `mark_extra_turns` is set, so are the
+/// moves that earn an extra turn (in a different color). Each move's recent expected
+/// scores (`score_history`, in the same order as `stats.options`) are drawn as a
+/// sparkline. Hovering over a hole shows its raw stats, including its PUCT score at
+/// the given `exploration_rate`, and highlights the opponent's holes that its move
+/// would capture from and the hole its last stone would land in. If swapping sides is
+/// available, a button for it is shown below the board. If `read_only` is set, the
+/// holes and the swap button can't be clicked (but the stats are still shown).
 pub fn add_annotated_game_state(
     ui: &mut Ui,
     game_state: &GameState,
     stats: Option<&StateStats>,
+    score_history: &[VecDeque<f32>],
+    exploration_rate: f32,
+    flipped: bool,
+    mark_extra_turns: bool,
+    read_only: bool,
+    hint: Option<usize>,
     mut make_move: impl FnMut(usize),
 ) {
-    // get the stats for each hole
-    let mut hole_stats = [None; HOLES_PER_SIDE];
+    // all the sparklines share a scale so that they can be compared
+    let all_scores = score_history.iter().flatten().copied();
+    let score_range = (
+        all_scores.clone().fold(f32::INFINITY, f32::min),
+        all_scores.fold(f32::NEG_INFINITY, f32::max),
+    );
+
+    // get the stats for each move (indexed by hole, or `SWAP_MOVE`)
+    let mut hole_stats = [None; MAX_MOVES];
     if let Some(stats) = stats {
-        for (hole_index, move_stats) in game_state.valid_moves().zip_eq(&stats.options) {
+        for (option_index, (hole_index, move_stats)) in
+            game_state.valid_moves().zip_eq(&stats.options).enumerate()
+        {
             hole_stats[hole_index] = Some(HoleStats {
                 parent_rollouts: stats.num_rollouts,
                 stats: move_stats,
+                score_history: score_history.get(option_index),
+                score_range,
+                exploration_rate,
             });
         }
     }
@@ -211,12 +1738,28 @@ pub fn add_annotated_game_state(
         ui.add_space(10.0);
         ui.spacing_mut().item_spacing.y = 10.0;
 
-        ui.add(player_label("Player 2", game_state.cur_player == Player::Player2));
-        ui.add(store_label(game_state.p2_state.store));
+        let bottom_player = if flipped {
+            Player::Player2
+        } else {
+            Player::Player1
+        };
+        let top_player = bottom_player.other();
+
+        // the move being hovered over is remembered from the previous frame, since the
+        // opponent's holes may be drawn before it's known
+        let hovered_hole_id = ui.make_persistent_id("hovered_hole");
+        let prev_hovered_hole = ui.data().get_temp::<usize>(hovered_hole_id);
+        let preview = prev_hovered_hole
+            .filter(|&hole_index| game_state.is_legal_move(hole_index))
+            .map(|hole_index| game_state.move_effects(hole_index));
+        let mut hovered_hole = None;
+
+        ui.add(player_label(top_player, game_state.cur_player == top_player));
+        ui.add(store_label(game_state.player(top_player).store));
 
         ui.columns(2, |columns| {
             let mut add_holes = |ui: &mut Ui, player: Player| {
-                let on_left = player == Player::Player1;
+                let on_left = player == bottom_player;
                 let player_state = game_state.player(player);
                 let layout = if on_left {
                     Layout::bottom_up(Align::RIGHT)
@@ -227,23 +1770,70 @@ pub fn add_annotated_game_state(
 
                 ui.set_enabled(is_active_side);
                 ui.with_layout(layout, |ui| {
-                    for (hole_index, &stones) in player_state.holes.iter().enumerate() {
+                    for (hole_index, &stones) in player_state.holes().iter().enumerate() {
                         let stats = hole_stats[hole_index].filter(|_| is_active_side);
-                        if ui.add(hole(stones, on_left, stats, is_game_over)).clicked() {
+                        let is_valid_move = is_active_side && game_state.is_legal_move(hole_index);
+                        let is_legal = !read_only && is_valid_move;
+                        let highlight = if is_active_side && hint == Some(hole_index) {
+                            HoleHighlight::Hint
+                        } else if mark_extra_turns
+                            && is_valid_move
+                            && game_state.move_effects(hole_index).extra_turn
+                        {
+                            HoleHighlight::ExtraTurn
+                        } else if !is_active_side
+                            && preview.is_some_and(|effects| effects.captures_from(hole_index))
+                        {
+                            HoleHighlight::Captured
+                        } else if preview.is_some_and(|effects| {
+                            effects.landing == Some(Landing::Hole(player, hole_index))
+                        }) {
+                            HoleHighlight::Landing
+                        } else {
+                            HoleHighlight::None
+                        };
+                        let hole = hole(stones, on_left, stats, is_game_over, is_legal, highlight);
+                        let response = ui.add(hole);
+                        if is_valid_move && response.hovered() {
+                            hovered_hole = Some(hole_index);
+                        }
+                        if response.clicked() {
                             make_move(hole_index);
                         }
                     }
                 });
             };
 
-            add_holes(&mut columns[1], Player::Player2);
+            add_holes(&mut columns[1], top_player);
 
             columns[0].set_height(columns[1].min_rect().height());
-            add_holes(&mut columns[0], Player::Player1);
+            add_holes(&mut columns[0], bottom_player);
         });
 
-        ui.add(store_label(game_state.p1_state.store));
-        ui.add(player_label("Player 1", game_state.cur_player == Player::Player1));
+        ui.add(store_label(game_state.player(bottom_player).store));
+        ui.add(player_label(bottom_player, game_state.cur_player == bottom_player));
+
+        if hovered_hole != prev_hovered_hole {
+            match hovered_hole {
+                Some(hole_index) => ui.data().insert_temp(hovered_hole_id, hole_index),
+                None => ui.data().remove::<usize>(hovered_hole_id),
+            }
+            ui.ctx().request_repaint();
+        }
+
+        if game_state.is_legal_move(SWAP_MOVE) {
+            let text = match hole_stats[SWAP_MOVE] {
+                Some(stats) => format!("Swap sides ({:+.1})", stats.stats.expected_score()),
+                None => "Swap sides".to_string(),
+            };
+            let mut button = Button::new(text);
+            if hint == Some(SWAP_MOVE) {
+                button = button.stroke(ui.visuals().selection.stroke);
+            }
+            if ui.add_enabled(!read_only, button).clicked() {
+                make_move(SWAP_MOVE);
+            }
+        }
 
         ui.add_space(0.0); // actually adds item_spacing
     });
@@ -302,8 +1892,33 @@ pub fn store_label(stones: u8) -> impl Widget {
     }
 }
 
+/// How a hole on the game board is outlined.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HoleHighlight {
+    None,
+
+    /// The hole's move is the hint.
+    Hint,
+
+    /// The hole's move earns an extra turn.
+    ExtraTurn,
+
+    /// The move being hovered over would capture this hole's stones.
+    Captured,
+
+    /// The last stone of the move being hovered over would land in this hole.
+    Landing,
+}
+
 /// A widget that displays the button representing a hole on the game board.
-pub fn hole_button(stones: u8, is_game_over: bool) -> impl Widget {
+/// The button is only enabled if selecting the hole `is_legal`, and it's outlined
+/// according to `highlight`.
+pub fn hole_button(
+    stones: u8,
+    is_game_over: bool,
+    is_legal: bool,
+    highlight: HoleHighlight,
+) -> impl Widget {
     move |ui: &mut Ui| {
         let base_size = vec2(22.0, 20.0);
         let padding = vec2(4.0, 4.0);
@@ -316,32 +1931,52 @@ pub fn hole_button(stones: u8, is_game_over: bool) -> impl Widget {
             text.into()
         };
 
-        let button = Button::new(text)
+        let mut button = Button::new(text)
             .min_size(button_size)
             .frame(!is_game_over || stones > 0);
+        let stroke = match highlight {
+            HoleHighlight::None => None,
+            HoleHighlight::Hint => Some(ui.visuals().selection.stroke),
+            HoleHighlight::ExtraTurn => Some(Stroke::new(1.5, ui.visuals().warn_fg_color)),
+            HoleHighlight::Captured => Some(Stroke::new(1.5, ui.visuals().error_fg_color)),
+            HoleHighlight::Landing => Some(Stroke::new(1.5, ui.visuals().hyperlink_color)),
+        };
+        if let Some(stroke) = stroke {
+            button = button.stroke(stroke);
+        }
 
-        ui.add_enabled(stones > 0, button)
+        ui.add_enabled(is_legal, button)
             .on_hover_cursor(CursorIcon::PointingHand)
     }
 }
 
-/// A widget that displays a bar indicating a quantity. Fills the available width.
+/// A widget that displays a bar indicating a quantity, filling in the given direction.
+/// Horizontal bars fill the available width, and vertical bars the available height.
 pub fn value_bar<N>(value: N, max_value: N, direction: Direction) -> impl Widget
 where
     N: Num + NumCast,
 {
     move |ui: &mut Ui| {
-        let width = ui.available_size_before_wrap().x;
-        let height = ui.spacing().interact_size.y;
-        let (outer_rect, response) = ui.allocate_exact_size(vec2(width, height), Sense::hover());
+        let thickness = ui.spacing().interact_size.y;
+        let available_size = ui.available_size_before_wrap();
+        let size = if direction.is_horizontal() {
+            vec2(available_size.x, thickness)
+        } else {
+            vec2(thickness, available_size.y)
+        };
+        let (outer_rect, response) = ui.allocate_exact_size(size, Sense::hover());
 
         if ui.is_rect_visible(response.rect) {
             let visuals = &ui.style().visuals;
-            let rounding = outer_rect.height() / 4.0;
+            let rounding = thickness / 4.0;
 
             let value: f32 = num_traits::cast(value).unwrap();
             let max_value: f32 = num_traits::cast(max_value).unwrap();
-            let proportion = (value / max_value).clamp(0.0, 1.0);
+            let proportion = if max_value == 0.0 {
+                0.0
+            } else {
+                (value / max_value).clamp(0.0, 1.0)
+            };
             let proportion = ui
                 .ctx()
                 .animate_value_with_time(response.id, proportion, 0.05);
@@ -349,13 +1984,18 @@ where
             ui.painter()
                 .rect(outer_rect, rounding, visuals.extreme_bg_color, Stroke::NONE);
 
-            let inner_size = vec2(outer_rect.width() * proportion, outer_rect.height());
+            let inner_size = if direction.is_horizontal() {
+                vec2(outer_rect.width() * proportion, outer_rect.height())
+            } else {
+                vec2(outer_rect.width(), outer_rect.height() * proportion)
+            };
             let inner_rect = match direction {
-                Direction::LeftToRight => Rect::from_min_size(outer_rect.min, inner_size),
-                Direction::RightToLeft => {
+                Direction::LeftToRight | Direction::TopDown => {
+                    Rect::from_min_size(outer_rect.min, inner_size)
+                }
+                Direction::RightToLeft | Direction::BottomUp => {
                     Rect::from_min_max(outer_rect.max - inner_size, outer_rect.max)
                 }
-                direction => unimplemented!("value_bar with direction {direction:?}"),
             };
             ui.painter()
                 .rect(inner_rect, rounding, visuals.selection.bg_fill, Stroke::NONE);
@@ -365,10 +2005,68 @@ where
     }
 }
 
+/// A widget that plots `values` as a small line chart, scaled so that `range` (min,
+/// max) spans its height.
+fn sparkline(values: &VecDeque<f32>, range: (f32, f32)) -> impl Widget + '_ {
+    move |ui: &mut Ui| {
+        let size = vec2(40.0, ui.spacing().interact_size.y);
+        let (rect, response) = ui.allocate_exact_size(size, Sense::hover());
+
+        if ui.is_rect_visible(rect) && values.len() >= 2 {
+            let (min, max) = range;
+            let x_step = rect.width() / (values.len() - 1) as f32;
+            let points = values
+                .iter()
+                .enumerate()
+                .map(|(i, &value)| {
+                    let proportion = if max > min {
+                        (value - min) / (max - min)
+                    } else {
+                        0.5
+                    };
+                    pos2(
+                        rect.left() + i as f32 * x_step,
+                        rect.bottom() - proportion * rect.height(),
+                    )
+                })
+                .collect();
+            let stroke = ui.visuals().widgets.noninteractive.fg_stroke;
+            ui.painter().add(Shape::line(points, stroke));
+        }
+
+        response
+    }
+}
+
 #[derive(Clone, Copy)]
 struct HoleStats<'a> {
     parent_rollouts: u32,
     stats: &'a OptionStats,
+    score_history: Option<&'a VecDeque<f32>>,
+    score_range: (f32, f32),
+    exploration_rate: f32,
+}
+
+impl HoleStats<'_> {
+    /// Returns the raw stats for the move, one per line, for a tooltip.
+    #[must_use]
+    fn details(&self) -> String {
+        // copy the fields out of the packed struct
+        let num_rollouts = self.stats.num_rollouts;
+        let total_score = self.stats.total_score;
+        let puct_score =
+            self.stats
+                .puct_score(self.parent_rollouts, self.exploration_rate, DEFAULT_FPU);
+        format!(
+            "{} rollouts\n{} total score\n{:+.2} expected score\n{:.2} PUCT score\n\
+             {:.1}% win probability",
+            num_rollouts.to_formatted_string(&Locale::en),
+            (total_score.round() as i64).to_formatted_string(&Locale::en),
+            self.stats.expected_score(),
+            puct_score,
+            self.stats.win_probability() * 100.0,
+        )
+    }
 }
 
 /// A widget that displays a hole in the game board along with its extra information.
@@ -377,6 +2075,8 @@ fn hole(
     on_left: bool,
     stats: Option<HoleStats>,
     is_game_over: bool,
+    is_legal: bool,
+    highlight: HoleHighlight,
 ) -> impl Widget + '_ {
     move |ui: &mut Ui| {
         let size = vec2(ui.available_width(), 22.0 + 4.0);
@@ -387,14 +2087,35 @@ fn hole(
         };
         let layout = Layout::from_main_dir_and_cross_align(direction, Align::Center);
         ui.allocate_ui_with_layout(size, layout, |ui| {
-            let button_response = ui.add(hole_button(stones, is_game_over));
+            let mut button_response =
+                ui.add(hole_button(stones, is_game_over, is_legal, highlight));
             if let Some(stats) = stats {
+                button_response = button_response.on_hover_ui(|ui| {
+                    ui.label(stats.details());
+                    if is_legal {
+                        ui.weak("Shift+click to preview the position after this move");
+                    }
+                });
                 ui.add_visible_ui(ui.is_enabled(), |ui| {
                     ui.add_space(22.0 + 4.0);
                     ui.add_sized(
                         vec2(32.4, 14.0),
                         Label::new(format!("{:+.1}", stats.stats.expected_score())),
-                    );
+                    )
+                    .on_hover_text(format!(
+                        "± {:.2} standard error\n{:.1} rollout standard deviation",
+                        stats.stats.score_std_error(),
+                        stats.stats.score_stddev(),
+                    ));
+                    ui.add_sized(
+                        vec2(32.4, 14.0),
+                        Label::new(format!("{:.0}%", stats.stats.win_probability() * 100.0)),
+                    )
+                    .on_hover_text("Win probability (draws count as half)");
+                    if let Some(score_history) = stats.score_history {
+                        ui.add(sparkline(score_history, stats.score_range))
+                            .on_hover_text("Recent expected scores");
+                    }
                     ui.add(value_bar(stats.stats.num_rollouts, stats.parent_rollouts, direction));
                 });
             }
@@ -403,3 +2124,34 @@ fn hole(
         .inner
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::self_play::{run_match, EngineConfig};
+
+    /// Returns a self-play engine that plays like the bot at the given difficulty (with
+    /// a shorter think time than the GUI's default, for the hard setting).
+    fn engine_at(difficulty: Difficulty) -> EngineConfig {
+        let (samples_per_move, temperature) = difficulty.settings();
+        EngineConfig {
+            samples_per_move,
+            temperature,
+            move_time: Duration::from_millis(200),
+            ..EngineConfig::default()
+        }
+    }
+
+    #[test]
+    #[ignore = "plays several full games, which takes a while"]
+    fn easy_plays_weaker_than_hard() {
+        let results =
+            run_match(&engine_at(Difficulty::Hard), &engine_at(Difficulty::Easy), 10, |_| {});
+        assert!(
+            results.score() > 0.5 && results.total_margin > 0,
+            "hard only scored {:.2} (margin {}) against easy",
+            results.score(),
+            results.total_margin,
+        );
+    }
+}
@@ -0,0 +1,15 @@
+//! The game engine: the rules, the search, and the tools built on them. The GUI (with
+//! its worker thread and network play) is part of the binary, so that using the engine
+//! doesn't require egui.
+
+pub mod analysis;
+pub mod benchmark;
+pub mod export;
+pub mod game_state;
+pub mod logging;
+pub mod lru;
+pub mod mcts;
+pub mod opening_book;
+pub mod perft;
+pub mod self_play;
+pub mod tablebase;
@@ -0,0 +1,49 @@
+//! A minimal logger that writes this crate's `log` messages (such as the search's
+//! per-ponder summaries) to stderr. When the level is off, the `log` macros only
+//! cost a comparison against the global maximum level.
+
+use std::sync::Mutex;
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+/// The level set by `init`, which `set_verbose(false)` returns to.
+static BASE_LEVEL: Mutex<LevelFilter> = Mutex::new(LevelFilter::Warn);
+
+struct StderrLogger;
+
+static LOGGER: StderrLogger = StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        // ignore the messages of dependencies (e.g. the windowing and rendering crates)
+        metadata.level() <= log::max_level()
+            && metadata.target().starts_with(env!("CARGO_CRATE_NAME"))
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("[{} {}] {}", record.level(), record.target(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Installs the logger, logging messages at `level` and above.
+pub fn init(level: LevelFilter) {
+    *BASE_LEVEL.lock().unwrap() = level;
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(level);
+    }
+}
+
+/// Raises the level to at least `Debug` (e.g. while the GUI's debug mode is on), or
+/// returns it to the level set by `init`.
+pub fn set_verbose(verbose: bool) {
+    let base_level = *BASE_LEVEL.lock().unwrap();
+    log::set_max_level(if verbose {
+        base_level.max(LevelFilter::Debug)
+    } else {
+        base_level
+    });
+}
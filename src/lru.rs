@@ -0,0 +1,261 @@
+use std::hash::Hash;
+
+use ahash::AHashMap;
+
+/// A marker for the end of the access-order list.
+const NIL: u32 = u32::MAX;
+
+/// The position of an entry in an `LruMap`'s slab (see `LruMap::get_mut_with_slot`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SlotId(u32);
+
+/// An entry of an `LruMap`, with its neighbors in the access-order list.
+struct Slot<K, V> {
+    key: K,
+    value: V,
+
+    /// The slot used next after this one, or `NIL` if this is the most recent.
+    newer: u32,
+
+    /// The slot used last before this one, or `NIL` if this is the least recent.
+    older: u32,
+}
+
+/// A hash map that keeps track of the order in which its entries were last used, so
+/// that the least recently used ones can be evicted without scanning the whole map.
+///
+/// The entries are stored in a slab and threaded onto a doubly linked list in access
+/// order; the hash map only holds each key's slot. Using an entry moves it to the
+/// front of the list, and evicting one takes it from the back, both in constant time.
+pub struct LruMap<K, V> {
+    slots: Vec<Slot<K, V>>,
+    indices: AHashMap<K, u32>,
+    newest: u32,
+    oldest: u32,
+}
+
+impl<K: Hash + Eq + Clone, V> Default for LruMap<K, V> {
+    fn default() -> Self {
+        Self {
+            slots: Vec::new(),
+            indices: AHashMap::new(),
+            newest: NIL,
+            oldest: NIL,
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> LruMap<K, V> {
+    /// Creates an empty map.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Returns the value for the given key, without marking it as used.
+    #[must_use]
+    pub fn peek(&self, key: &K) -> Option<&V> {
+        let index = *self.indices.get(key)?;
+        Some(&self.slots[index as usize].value)
+    }
+
+    /// Returns the value for the given key, marking it as the most recently used.
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.get_mut_with_slot(key).map(|(_, value)| value)
+    }
+
+    /// Like `get_mut`, but also returns the entry's slot, for getting it again with
+    /// `get_slot_mut` without looking up the key.
+    pub fn get_mut_with_slot(&mut self, key: &K) -> Option<(SlotId, &mut V)> {
+        let index = *self.indices.get(key)?;
+        self.touch(index);
+        Some((SlotId(index), &mut self.slots[index as usize].value))
+    }
+
+    /// Returns the value in the given slot, marking it as the most recently used. The
+    /// slot must have come from `get_mut_with_slot`, with no entries evicted since
+    /// (which can move entries to other slots).
+    pub fn get_slot_mut(&mut self, slot: SlotId) -> &mut V {
+        self.touch(slot.0);
+        &mut self.slots[slot.0 as usize].value
+    }
+
+    /// Inserts (or replaces) the value for the given key as the most recently used,
+    /// and returns a reference to it.
+    pub fn insert(&mut self, key: K, value: V) -> &mut V {
+        let index = match self.indices.get(&key) {
+            Some(&index) => {
+                self.slots[index as usize].value = value;
+                self.touch(index);
+                index
+            }
+            None => {
+                let index = u32::try_from(self.slots.len())
+                    .ok()
+                    .filter(|&index| index != NIL)
+                    .expect("too many entries for an LruMap");
+                self.slots.push(Slot {
+                    key: key.clone(),
+                    value,
+                    newer: NIL,
+                    older: NIL,
+                });
+                self.indices.insert(key, index);
+                self.push_newest(index);
+                index
+            }
+        };
+        &mut self.slots[index as usize].value
+    }
+
+    /// Evicts the least recently used entries until at most `max_len` remain.
+    pub fn evict_to(&mut self, max_len: usize) {
+        while self.len() > max_len {
+            self.remove_oldest();
+        }
+    }
+
+    /// Moves the given slot to the front of the access-order list.
+    fn touch(&mut self, index: u32) {
+        if index != self.newest {
+            self.unlink(index);
+            self.push_newest(index);
+        }
+    }
+
+    /// Links the given (unlinked) slot in as the most recently used.
+    fn push_newest(&mut self, index: u32) {
+        let slot = &mut self.slots[index as usize];
+        slot.newer = NIL;
+        slot.older = self.newest;
+        match self.newest {
+            NIL => self.oldest = index,
+            newest => self.slots[newest as usize].newer = index,
+        }
+        self.newest = index;
+    }
+
+    /// Removes the given slot from the access-order list, joining up its neighbors.
+    fn unlink(&mut self, index: u32) {
+        let Slot { newer, older, .. } = self.slots[index as usize];
+        match newer {
+            NIL => self.newest = older,
+            newer => self.slots[newer as usize].older = older,
+        }
+        match older {
+            NIL => self.oldest = newer,
+            older => self.slots[older as usize].newer = newer,
+        }
+    }
+
+    /// Removes the least recently used entry, if there is one. The last slot is moved
+    /// into its place to keep the slab compact.
+    fn remove_oldest(&mut self) {
+        let index = self.oldest;
+        if index == NIL {
+            return;
+        }
+        self.unlink(index);
+        let removed = self.slots.swap_remove(index as usize);
+        self.indices.remove(&removed.key);
+
+        // relink the slot that was moved into the gap (unless the removed one was last)
+        if let Some(moved) = self.slots.get(index as usize) {
+            let Slot { newer, older, .. } = *moved;
+            *self.indices.get_mut(&moved.key).unwrap() = index;
+            match newer {
+                NIL => self.newest = index,
+                newer => self.slots[newer as usize].older = index,
+            }
+            match older {
+                NIL => self.oldest = index,
+                older => self.slots[older as usize].newer = index,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the keys from least to most recently used, checking that the list is
+    /// linked the same way in both directions and that every key maps to its slot.
+    fn keys_by_age(map: &LruMap<u32, u32>) -> Vec<u32> {
+        let mut keys = Vec::new();
+        let mut index = map.oldest;
+        let mut older = NIL;
+        while index != NIL {
+            let slot = &map.slots[index as usize];
+            assert_eq!(slot.older, older, "broken link at key {}", slot.key);
+            assert_eq!(map.indices[&slot.key], index, "wrong index for key {}", slot.key);
+            keys.push(slot.key);
+            older = index;
+            index = slot.newer;
+        }
+        assert_eq!(map.newest, older);
+        assert_eq!(keys.len(), map.len());
+        keys
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used_entries() {
+        let mut map = LruMap::new();
+        for key in 1..=5 {
+            map.insert(key, key * 10);
+        }
+        assert_eq!(keys_by_age(&map), [1, 2, 3, 4, 5]);
+
+        // getting an entry marks it as used, but peeking doesn't
+        assert_eq!(map.get_mut(&2), Some(&mut 20));
+        assert_eq!(map.peek(&3), Some(&30));
+        map.insert(4, 41);
+        assert_eq!(keys_by_age(&map), [1, 3, 5, 2, 4]);
+
+        map.evict_to(3);
+        assert_eq!(keys_by_age(&map), [5, 2, 4]);
+        assert_eq!(map.peek(&1), None);
+        assert_eq!(map.peek(&3), None);
+        assert_eq!(map.peek(&4), Some(&41));
+
+        map.evict_to(0);
+        assert!(map.is_empty());
+        assert!(keys_by_age(&map).is_empty());
+    }
+
+    #[test]
+    fn relinks_entries_moved_by_eviction() {
+        let mut map = LruMap::new();
+        for key in 1..=4 {
+            map.insert(key, key * 10);
+        }
+
+        // evicting the entry in the first slot moves the last slot's entry into its
+        // place: first from the middle of the list, and then from its newest end
+        map.get_mut(&4);
+        map.get_mut(&2);
+        map.evict_to(3);
+        assert_eq!(keys_by_age(&map), [3, 4, 2]);
+        map.get_mut(&3);
+        map.evict_to(2);
+        assert_eq!(keys_by_age(&map), [2, 3]);
+
+        // the moved entries are still found and used as normal
+        assert_eq!(map.get_mut(&2), Some(&mut 20));
+        map.insert(5, 50);
+        assert_eq!(keys_by_age(&map), [3, 2, 5]);
+        map.evict_to(1);
+        assert_eq!(keys_by_age(&map), [5]);
+        assert_eq!(map.peek(&5), Some(&50));
+    }
+}
@@ -1,11 +1,158 @@
+use std::time::Duration;
+
 use egui::vec2;
+use itertools::Itertools;
+
+// the engine modules come from the library; importing them here lets the GUI modules
+// refer to them through `crate::`
+use mancala_bot::{
+    analysis, benchmark, export, game_state, logging, mcts, opening_book, perft, self_play,
+    tablebase,
+};
 
-pub mod game_state;
 pub mod gui;
-pub mod mcts;
+pub mod net;
 pub mod worker;
 
+/// Returns the value following the given flag in the command-line arguments, if present.
+fn arg_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter().position(|arg| arg == flag).map(|index| {
+        args.get(index + 1)
+            .expect("missing value for flag")
+            .as_str()
+    })
+}
+
+/// Parses the value following the given flag, or returns `default` if it isn't present.
+fn parse_arg<T: std::str::FromStr>(args: &[String], flag: &str, default: T) -> T {
+    arg_value(args, flag).map_or(default, |value| {
+        value
+            .parse()
+            .unwrap_or_else(|_| panic!("invalid value for {flag}: {value:?}"))
+    })
+}
+
 fn main() {
+    let args = std::env::args().skip(1).collect_vec();
+    logging::init(parse_arg(&args, "--log-level", log::LevelFilter::Warn));
+
+    if args.iter().any(|arg| arg == "--benchmark") {
+        let results = match arg_value(&args, "--benchmark-samples") {
+            Some(samples) => benchmark::run_samples(
+                samples.parse().expect("invalid number of samples"),
+                2_000_000,
+            ),
+            None => benchmark::run(Duration::from_secs(5), 2_000_000),
+        };
+        println!("{}", results.summary_line());
+        return;
+    }
+
+    if args.iter().any(|arg| arg == "--analyze") {
+        let budget = match arg_value(&args, "--analyze-samples") {
+            Some(samples) => {
+                analysis::Budget::Samples(samples.parse().expect("invalid number of samples"))
+            }
+            None => analysis::Budget::Time(Duration::from_millis(parse_arg(
+                &args,
+                "--analyze-ms",
+                1000,
+            ))),
+        };
+        let game_state = game_state::GameState::default();
+        println!("{}", analysis::analyze(&game_state, budget));
+        return;
+    }
+
+    if let Some(depth) = arg_value(&args, "--perft") {
+        let depth = depth.parse().expect("invalid depth");
+        let game_state = game_state::GameState::default();
+        println!("perft({depth}) = {}", perft::perft(&game_state, depth));
+        return;
+    }
+
+    if let Some(depth) = arg_value(&args, "--branching") {
+        let depth = depth.parse().expect("invalid depth");
+        let game_state = game_state::GameState::default();
+        print!("{}", perft::branching_stats(&game_state, depth));
+        return;
+    }
+
+    if let Some(path) = arg_value(&args, "--generate-book") {
+        let max_plies = parse_arg(&args, "--book-plies", 4);
+        let max_entries = parse_arg(&args, "--book-size", 10_000);
+        let ponder_time = Duration::from_millis(parse_arg(&args, "--ponder-ms", 1000));
+        let book = opening_book::OpeningBook::generate(max_plies, ponder_time, max_entries);
+        book.save(path).expect("failed to save opening book");
+        println!("Saved {} book moves to {path}", book.len());
+        return;
+    }
+
+    if args.iter().any(|arg| arg == "--headless") {
+        // the engine plays itself, so the results are from Player 1's perspective
+        let num_games: u32 = parse_arg(&args, "--games", 1);
+        let engine = self_play::EngineConfig {
+            move_time: Duration::from_millis(parse_arg(&args, "--ponder-ms", 100)),
+            ..Default::default()
+        };
+        let mut results = self_play::MatchResults::default();
+        for game_index in 0..num_games {
+            let outcome = self_play::play_game(&engine, &engine);
+            println!("game {}: {outcome}", game_index + 1);
+            match outcome.winner() {
+                Some(game_state::Player::Player1) => results.wins += 1,
+                Some(game_state::Player::Player2) => results.losses += 1,
+                None => results.draws += 1,
+            }
+            results.total_margin += i64::from(outcome.score());
+        }
+        println!(
+            "player 1: {} wins, {} draws, {} losses, {:+.1} average margin",
+            results.wins,
+            results.draws,
+            results.losses,
+            results.average_margin(),
+        );
+        return;
+    }
+
+    if let Some(num_games) = arg_value(&args, "--match") {
+        let num_games = num_games.parse().expect("invalid number of games");
+        let move_time = Duration::from_millis(parse_arg(&args, "--move-ms", 100));
+        let [engine_a, engine_b] = ["a", "b"].map(|name| {
+            let defaults = self_play::EngineConfig::default();
+            self_play::EngineConfig {
+                use_rave: args.iter().any(|arg| *arg == format!("--{name}-rave")),
+                rave_equivalence: parse_arg(
+                    &args,
+                    &format!("--{name}-rave-equivalence"),
+                    defaults.rave_equivalence,
+                ),
+                fpu: parse_arg(&args, &format!("--{name}-fpu"), defaults.fpu),
+                exploration_rate: parse_arg(
+                    &args,
+                    &format!("--{name}-exploration"),
+                    defaults.exploration_rate,
+                ),
+                discount: parse_arg(&args, &format!("--{name}-discount"), defaults.discount),
+                temperature: parse_arg(
+                    &args,
+                    &format!("--{name}-temperature"),
+                    defaults.temperature,
+                ),
+                move_time,
+                samples_per_move: arg_value(&args, &format!("--{name}-samples"))
+                    .map(|value| value.parse().expect("invalid sample budget")),
+                ..defaults
+            }
+        });
+        let results = self_play::run_match(&engine_a, &engine_b, num_games, |results| {
+            eprintln!("{}", results.summary_line());
+        });
+        println!("{}", results.summary_line());
+        return;
+    }
+
     let native_options = eframe::NativeOptions {
         min_window_size: Some(vec2(300.0, 200.0)),
         initial_window_size: Some(vec2(1000.0, 650.0)),
@@ -1,43 +1,146 @@
-use ahash::AHashMap;
 use arrayvec::ArrayVec;
 use itertools::Itertools;
 use ordered_float::NotNan;
-use rand::seq::SliceRandom;
-use rand::{thread_rng, Rng};
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::rngs::StdRng;
+use rand::seq::{IteratorRandom, SliceRandom};
+use rand::{thread_rng, Rng, SeedableRng};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use static_assertions::const_assert_eq;
 
-use std::collections::hash_map::Entry;
+use std::cmp::Reverse;
 use std::iter;
+use std::mem;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-use crate::game_state::{GameState, Player, HOLES_PER_SIDE};
+use crate::game_state::{GameState, PackedState, Player, Score, MAX_MOVES};
+use crate::lru::LruMap;
+use crate::tablebase::Tablebase;
+
+/// The maximum number of moves in a rollout, as a safeguard against rule variants
+/// where games might not terminate. Standard games are far shorter than this.
+const MAX_ROLLOUT_PLIES: u32 = 1000;
+
+/// A suggested value for `MCTSContext::discount` when discounting is enabled.
+pub const DEFAULT_DISCOUNT: f32 = 0.99;
+
+/// The maximum number of moves a sample makes within the search tree, as a safeguard
+/// against rule variants (like `Ruleset::Oware`) where positions can repeat, and so
+/// the tree can contain cycles.
+const MAX_TREE_DEPTH: u32 = 500;
+
+/// The default value of `MCTSContext::exploration_rate`.
+pub const DEFAULT_EXPLORATION_RATE: f32 = 100.0;
+
+/// The default value of `MCTSContext::fpu`.
+pub const DEFAULT_FPU: f32 = 0.0;
+
+/// A suggested node cache size limit. Each cached state takes roughly 300 bytes (mostly
+/// its `OptionStats`), so this is about 300 MB.
+pub const DEFAULT_CACHE_SIZE_LIMIT: usize = 1_000_000;
+
+/// The number of rollouts that have been cut off at `MAX_ROLLOUT_PLIES` (or samples
+/// cut off at `MAX_TREE_DEPTH`).
+static NUM_TRUNCATED_ROLLOUTS: AtomicU64 = AtomicU64::new(0);
+
+/// Returns the number of rollouts (across all threads) that have been cut off for
+/// running too long.
+#[must_use]
+pub fn num_truncated_rollouts() -> u64 {
+    NUM_TRUNCATED_ROLLOUTS.load(Ordering::Relaxed)
+}
 
 /// Performs a randomized rollout from the given state and returns the final
-/// score for Player 1.
+/// score for Player 1, along with the number of plies played.
 #[must_use]
-pub fn compute_rollout_score(mut game_state: GameState) -> i8 {
-    let mut rng = thread_rng();
+pub fn compute_rollout_score(game_state: GameState) -> (Score, u32) {
+    compute_rollout(game_state, &mut PlayedMoves::default(), &mut thread_rng())
+}
 
-    loop {
+/// Performs a randomized rollout from the given state and returns the final
+/// score for Player 1, along with the number of plies played. Each move made during
+/// the rollout is recorded in `played_moves`. If the rollout runs for more than
+/// `MAX_ROLLOUT_PLIES` moves, it's cut off and the current score difference is
+/// returned instead. The moves are chosen with `rng`.
+pub fn compute_rollout(
+    mut game_state: GameState,
+    played_moves: &mut PlayedMoves,
+    rng: &mut impl Rng,
+) -> (Score, u32) {
+    for plies in 0..MAX_ROLLOUT_PLIES {
         if let Some(score) = game_state.result() {
-            return score;
+            return (score, plies);
         }
 
-        let valid_moves = game_state
-            .valid_moves()
-            .collect::<ArrayVec<_, HOLES_PER_SIDE>>();
+        let valid_moves = game_state.valid_moves().collect::<ArrayVec<_, MAX_MOVES>>();
         let random_move = *valid_moves
-            .choose(&mut rng)
+            .choose(rng)
             .expect("GameState should have at least one valid move");
+        played_moves.insert(game_state.cur_player, random_move);
         game_state.make_move(random_move);
     }
+
+    let score = game_state
+        .result()
+        .unwrap_or_else(|| truncated_score(&game_state));
+    (score, MAX_ROLLOUT_PLIES)
 }
 
-pub fn get_best_options(option_stats_arr: &[OptionStats]) -> impl Iterator<Item = usize> + '_ {
-    let max_visit_count = option_stats_arr
+/// Returns the score difference (for Player 1) of a state where a game is being cut
+/// off, and counts the truncation.
+fn truncated_score(game_state: &GameState) -> Score {
+    NUM_TRUNCATED_ROLLOUTS.fetch_add(1, Ordering::Relaxed);
+    let p1_score = game_state.player(Player::Player1).score();
+    let p2_score = game_state.player(Player::Player2).score();
+    Score::from(p1_score) - Score::from(p2_score)
+}
+
+/// The set of holes that each player has played from during a simulation.
+/// Used to gather AMAF (All-Moves-As-First) statistics.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PlayedMoves {
+    p1_holes: u32,
+    p2_holes: u32,
+}
+
+impl PlayedMoves {
+    /// Records that the given player played from the given hole.
+    pub fn insert(&mut self, player: Player, hole: usize) {
+        *self.holes_mut(player) |= 1 << hole;
+    }
+
+    /// Returns whether the given player played from the given hole.
+    #[must_use]
+    pub fn contains(&self, player: Player, hole: usize) -> bool {
+        let holes = match player {
+            Player::Player1 => self.p1_holes,
+            Player::Player2 => self.p2_holes,
+        };
+        holes & (1 << hole) != 0
+    }
+
+    fn holes_mut(&mut self, player: Player) -> &mut u32 {
+        match player {
+            Player::Player1 => &mut self.p1_holes,
+            Player::Player2 => &mut self.p2_holes,
+        }
+    }
+}
+
+/// Returns the highest visit count of any of the given options.
+fn get_max_visit_count(option_stats_arr: &[OptionStats]) -> u32 {
+    option_stats_arr
         .iter()
         .map(|option_stats| option_stats.num_rollouts)
         .max()
-        .expect("option_stats_arr is empty");
+        .expect("option_stats_arr is empty")
+}
+
+pub fn get_best_options(option_stats_arr: &[OptionStats]) -> impl Iterator<Item = usize> + '_ {
+    let max_visit_count = get_max_visit_count(option_stats_arr);
 
     option_stats_arr
         .iter()
@@ -46,11 +149,116 @@ pub fn get_best_options(option_stats_arr: &[OptionStats]) -> impl Iterator<Item
         .map(|(option_index, _)| option_index)
 }
 
+/// Returns a single best option index, breaking ties between the most-visited options
+/// by highest expected score and then lowest index (i.e. lowest hole).
+#[must_use]
+pub fn get_best_option_deterministic(option_stats_arr: &[OptionStats]) -> usize {
+    get_best_options(option_stats_arr)
+        .min_by_key(|&option_index| {
+            let expected_score = option_stats_arr[option_index].expected_score();
+            (Reverse(expected_score), option_index)
+        })
+        .unwrap()
+}
+
+/// The temperature below which `sample_move_with_temperature` just picks a best option.
+const MIN_TEMPERATURE: f32 = 0.01;
+
+/// Randomly picks an option index, with probability proportional to
+/// `num_rollouts^(1/temperature)`. At a temperature of 1 this samples in proportion
+/// to the visit counts; as the temperature approaches 0, this reduces to picking
+/// uniformly among the best options (see `get_best_options`).
+pub fn sample_move_with_temperature(
+    stats: &StateStats,
+    temperature: f32,
+    rng: &mut impl Rng,
+) -> usize {
+    if temperature < MIN_TEMPERATURE {
+        return get_best_options(&stats.options).choose(rng).unwrap();
+    }
+
+    // normalize by the maximum visit count so that the weights can't overflow
+    let max_visit_count = get_max_visit_count(&stats.options);
+    if max_visit_count == 0 {
+        return rng.gen_range(0..stats.options.len());
+    }
+    let weights = stats.options.iter().map(|option_stats| {
+        (option_stats.num_rollouts as f32 / max_visit_count as f32).powf(1.0 / temperature)
+    });
+    WeightedIndex::new(weights)
+        .expect("option weights should be valid")
+        .sample(rng)
+}
+
 #[derive(Debug, Clone, Copy, Default)]
-#[repr(packed)]
+#[repr(C, packed)]
 pub struct OptionStats {
     pub num_rollouts: u32,
-    pub total_score: i64,
+
+    /// The sum of the (possibly discounted; see `MCTSContext::discount`) rollout scores.
+    pub total_score: f64,
+
+    /// The sum of the squares of the rollout scores, for measuring their spread.
+    pub total_squared_score: f64,
+
+    /// The number of rollouts won by the chooser.
+    pub num_wins: u32,
+
+    /// The number of drawn rollouts.
+    pub num_draws: u32,
+
+    /// The exact score for the chooser if this option's outcome has been solved.
+    pub proven: Option<Score>,
+}
+
+// a copy of this is kept for every option of every cached state, so keep an eye on it
+const_assert_eq!(mem::size_of::<OptionStats>(), 32);
+
+/// An unpacked mirror of `OptionStats` used for (de)serialization, since the fields
+/// of a packed struct can't be borrowed.
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct OptionStatsRepr {
+    num_rollouts: u32,
+    total_score: f64,
+    #[serde(default)]
+    total_squared_score: f64,
+    #[serde(default)]
+    num_wins: u32,
+    #[serde(default)]
+    num_draws: u32,
+    proven: Option<Score>,
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for OptionStats {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // copy the fields out rather than referencing them
+        let repr = OptionStatsRepr {
+            num_rollouts: self.num_rollouts,
+            total_score: self.total_score,
+            total_squared_score: self.total_squared_score,
+            num_wins: self.num_wins,
+            num_draws: self.num_draws,
+            proven: self.proven,
+        };
+        repr.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for OptionStats {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = OptionStatsRepr::deserialize(deserializer)?;
+        Ok(OptionStats {
+            num_rollouts: repr.num_rollouts,
+            total_score: repr.total_score,
+            total_squared_score: repr.total_squared_score,
+            num_wins: repr.num_wins,
+            num_draws: repr.num_draws,
+            proven: repr.proven,
+        })
+    }
 }
 
 impl OptionStats {
@@ -60,11 +268,34 @@ impl OptionStats {
         if self.num_rollouts == 0 {
             NotNan::new(0.0).unwrap()
         } else {
-            let expected_score = (self.total_score as f32) / (self.num_rollouts as f32);
+            let expected_score = (self.total_score / f64::from(self.num_rollouts)) as f32;
             NotNan::new(expected_score).expect("expected score is NaN")
         }
     }
 
+    /// Returns the standard deviation of the rollout scores for this option
+    /// (0 if it has fewer than two rollouts).
+    #[must_use]
+    pub fn score_stddev(&self) -> f32 {
+        if self.num_rollouts < 2 {
+            return 0.0;
+        }
+        let num_rollouts = self.num_rollouts as f64;
+        let mean = self.total_score / num_rollouts;
+        let mean_square = self.total_squared_score / num_rollouts;
+        (mean_square - mean * mean).max(0.0).sqrt() as f32
+    }
+
+    /// Returns the standard error of `expected_score`, i.e. how far it's likely
+    /// to be from the true expected score of the rollouts.
+    #[must_use]
+    pub fn score_std_error(&self) -> f32 {
+        if self.num_rollouts < 2 {
+            return 0.0;
+        }
+        self.score_stddev() / (self.num_rollouts as f32).sqrt()
+    }
+
     /// The UCB1 score for a choice.
     /// https://gibberblot.github.io/rl-notes/single-agent/multi-armed-bandits.html
     #[must_use]
@@ -73,52 +304,298 @@ impl OptionStats {
             + (2.0 * (rollout_num as f32).ln() / (self.num_rollouts as f32)).sqrt()
     }
 
+    /// Returns the estimated expected score for this option, or the first-play
+    /// urgency `fpu` if it has no rollouts yet.
+    #[must_use]
+    pub fn value_estimate(&self, fpu: f32) -> NotNan<f32> {
+        if self.num_rollouts == 0 {
+            NotNan::new(fpu).expect("fpu is NaN")
+        } else {
+            self.expected_score()
+        }
+    }
+
     /// A variant of the PUCT score, similar to that used in AlphaZero.
+    /// Unvisited options are valued at `fpu` rather than their (empty) expected score;
+    /// since their exploration term is already at its largest, a high `fpu` makes the
+    /// search try every option before revisiting any, while a low one lets it keep
+    /// exploiting options that already look good.
+    #[must_use]
+    pub fn puct_score(&self, parent_rollouts: u32, exploration_rate: f32, fpu: f32) -> NotNan<f32> {
+        self.value_estimate(fpu) + self.exploration_score(parent_rollouts, exploration_rate)
+    }
+
+    /// The PUCT score, with the expected score blended with the given AMAF stats
+    /// for this option (see `rave_score`).
+    #[must_use]
+    pub fn puct_rave_score(
+        &self,
+        amaf_stats: &AmafStats,
+        rave_equivalence: f32,
+        parent_rollouts: u32,
+        exploration_rate: f32,
+        fpu: f32,
+    ) -> NotNan<f32> {
+        self.rave_score(amaf_stats, rave_equivalence, fpu)
+            + self.exploration_score(parent_rollouts, exploration_rate)
+    }
+
+    /// The expected score blended with the given AMAF stats for this option.
+    /// The AMAF weight starts at 1 and decays as this option gets more rollouts;
+    /// `rave_equivalence` is the number of rollouts at which the two are weighted equally.
+    #[must_use]
+    pub fn rave_score(
+        &self,
+        amaf_stats: &AmafStats,
+        rave_equivalence: f32,
+        fpu: f32,
+    ) -> NotNan<f32> {
+        let num_rollouts = self.num_rollouts as f32;
+        let beta = (rave_equivalence / (3.0 * num_rollouts + rave_equivalence)).sqrt();
+        self.value_estimate(fpu) * (1.0 - beta) + amaf_stats.value_estimate(fpu) * beta
+    }
+
+    /// The exploration term of the PUCT score, scaled by `exploration_rate`.
+    #[must_use]
+    fn exploration_score(&self, parent_rollouts: u32, exploration_rate: f32) -> f32 {
+        exploration_rate * (parent_rollouts as f32).sqrt() / ((1 + self.num_rollouts) as f32)
+    }
+
+    /// Returns whether this option is proven to lose for the chooser.
     #[must_use]
-    pub fn puct_score(&self, parent_rollouts: u32) -> NotNan<f32> {
-        let exploration_rate = 100.0; // TODO: make this a tunable parameter
-        let exploration_score =
-            exploration_rate * (parent_rollouts as f32).sqrt() / ((1 + self.num_rollouts) as f32);
-        self.expected_score() + exploration_score
+    pub fn is_proven_loss(&self) -> bool {
+        self.proven.is_some_and(|proven_score| proven_score < 0)
     }
+
+    /// Returns the fraction of this option's rollouts won by the chooser, counting
+    /// draws as half a win (or the exact value, if the option is proven).
+    #[must_use]
+    pub fn win_probability(&self) -> f32 {
+        // copy the field out rather than referencing it (see `OptionStatsRepr`)
+        let proven = self.proven;
+        if let Some(proven_score) = proven {
+            return match proven_score.signum() {
+                1 => 1.0,
+                -1 => 0.0,
+                _ => 0.5,
+            };
+        }
+        if self.num_rollouts == 0 {
+            return 0.5;
+        }
+        (self.num_wins as f32 + 0.5 * self.num_draws as f32) / self.num_rollouts as f32
+    }
+
+    /// Records the result of a rollout (from the perspective of the chooser). Only
+    /// the sign of the score decides whether it counts as a win, so discounting
+    /// doesn't affect the win counts.
+    fn add_rollout(&mut self, score: f64) {
+        self.num_rollouts += 1;
+        self.total_score += score;
+        self.total_squared_score += score * score;
+        if score > 0.0 {
+            self.num_wins += 1;
+        } else if score == 0.0 {
+            self.num_draws += 1;
+        }
+    }
+}
+
+/// The AMAF (All-Moves-As-First) stats for an option: only what `OptionStats::rave_score`
+/// needs, since there's a set of these for each option when RAVE is enabled.
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C, packed)]
+pub struct AmafStats {
+    pub num_rollouts: u32,
+
+    /// The sum of the (possibly discounted) rollout scores.
+    pub total_score: f64,
 }
 
+impl AmafStats {
+    /// Returns the average rollout score, or the first-play urgency `fpu` if there
+    /// are no rollouts yet.
+    #[must_use]
+    pub fn value_estimate(&self, fpu: f32) -> NotNan<f32> {
+        let value_estimate = if self.num_rollouts == 0 {
+            fpu
+        } else {
+            (self.total_score / f64::from(self.num_rollouts)) as f32
+        };
+        NotNan::new(value_estimate).expect("AMAF value estimate is NaN")
+    }
+
+    fn add_rollout(&mut self, score: f64) {
+        self.num_rollouts += 1;
+        self.total_score += score;
+    }
+}
+
+/// The search statistics for a state, with one entry in `options` per valid move.
+///
+/// The search only caches states with at least two valid moves; forced states (with
+/// exactly one) are passed straight through, so `MCTSContext::stats_for` returns None
+/// for them. Use `StateStats::forced` where stats for such a state are needed anyway.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct StateStats {
-    pub options: ArrayVec<OptionStats, HOLES_PER_SIDE>,
+    pub options: ArrayVec<OptionStats, MAX_MOVES>,
+
+    /// AMAF stats for each option, crediting every rollout in which the chooser
+    /// played that hole at any point after this state. Only kept if RAVE was enabled
+    /// when the state was first explored, since they'd otherwise go unused.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub amaf_options: Option<Box<[AmafStats]>>,
+
     pub num_rollouts: u32,
-    last_visit_ply: u32,
+
+    /// The exact score for the chooser if this state has been solved (i.e. all of
+    /// its options are proven).
+    pub proven: Option<Score>,
 }
 
 impl StateStats {
     #[must_use]
-    fn new(num_options: usize, current_ply: u32) -> Self {
-        debug_assert!(num_options > 1, "Expanded a state with less than 2 options");
+    fn new(num_options: usize, use_rave: bool) -> Self {
+        debug_assert!(num_options > 0, "Created stats for a state with no options");
         Self {
             options: iter::repeat_with(OptionStats::default)
                 .take(num_options)
                 .collect(),
+            amaf_options: use_rave.then(|| vec![AmafStats::default(); num_options].into()),
             num_rollouts: 0,
-            last_visit_ply: current_ply,
+            proven: None,
         }
     }
+
+    /// Returns (empty) stats for a forced state, i.e. one with a single valid move.
+    #[must_use]
+    pub fn forced() -> Self {
+        Self::new(1, false)
+    }
+
+    /// Returns the index of the most-visited option, breaking ties as described in
+    /// `get_best_option_deterministic`.
+    #[must_use]
+    pub fn best_option(&self) -> usize {
+        get_best_option_deterministic(&self.options)
+    }
+
+    /// Returns the index of the option with the highest expected score, ignoring
+    /// unvisited options (unless no option has been visited). Ties are broken by
+    /// highest visit count and then lowest index.
+    #[must_use]
+    pub fn best_option_by_score(&self) -> usize {
+        self.options
+            .iter()
+            .enumerate()
+            .max_by_key(|(option_index, option_stats)| {
+                let num_rollouts = option_stats.num_rollouts;
+                (
+                    num_rollouts > 0,
+                    option_stats.expected_score(),
+                    num_rollouts,
+                    Reverse(*option_index),
+                )
+            })
+            .map(|(option_index, _)| option_index)
+            .expect("stats have no options")
+    }
+}
+
+/// A position along the search's principal line (see `MCTSContext::principal_line`).
+#[derive(Debug, Clone)]
+pub struct LineStep {
+    /// The move that led to this position.
+    pub hole: usize,
+
+    pub game_state: GameState,
+
+    /// The stats for this position, or None if it's terminal or hasn't been explored.
+    pub stats: Option<StateStats>,
+}
+
+/// Statistics about the samples performed by a call to `MCTSContext::ponder`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PonderStats {
+    /// The number of iterations/samples performed.
+    pub num_samples: usize,
+
+    /// The sum of the search depths of all samples.
+    pub sum_depths: u32,
+
+    /// The maximum search depth of any sample.
+    pub max_depth: u32,
+
+    /// The number of nodes added to the cache.
+    pub new_nodes: usize,
 }
 
 pub struct MCTSContext {
-    explored_states: AHashMap<GameState, StateStats>,
-    current_ply: u32,
+    explored_states: LruMap<PackedState, StateStats>,
 
-    /// The (approximate) limit on the number of nodes to retain in the cache.
+    /// The limit on the number of nodes to retain in the cache. The least recently
+    /// visited nodes are evicted down to it before each search, so it's only exceeded
+    /// by the nodes added during a search.
     pub cache_size_limit: usize,
+
+    /// Whether to blend AMAF (RAVE) stats into the selection score.
+    pub use_rave: bool,
+
+    /// The number of rollouts of an option at which its own stats and its AMAF
+    /// stats are weighted equally during selection.
+    pub rave_equivalence: f32,
+
+    /// The first-play urgency: the value assumed during selection for options that
+    /// haven't been tried yet. See `OptionStats::puct_score`.
+    pub fpu: f32,
+
+    /// How strongly selection favors rarely-visited options over ones that already
+    /// look good (the constant in the exploration term of `OptionStats::puct_score`).
+    /// It only affects future selections, so it can be changed between searches.
+    pub exploration_rate: f32,
+
+    /// The factor applied to a rollout's score for each ply it took to finish the game
+    /// (from the state being updated), so that faster wins (and slower losses) score
+    /// higher. 1 disables discounting.
+    pub discount: f32,
+
+    /// A tablebase of exact values to use for positions with few stones left, if any.
+    pub tablebase: Option<Arc<Tablebase>>,
+
+    /// The source of randomness for the search (for picking options at new nodes, and
+    /// for rollouts).
+    rng: StdRng,
 }
 
 impl MCTSContext {
+    /// Creates a context with an empty cache, whose randomness is seeded from the OS.
     #[must_use]
     pub fn new(cache_size_limit: usize) -> Self {
+        Self::with_rng(cache_size_limit, StdRng::from_entropy())
+    }
+
+    /// Creates a context with an empty cache, whose randomness comes from the given
+    /// seed. Two contexts created with the same seed and given the same sequence of
+    /// calls build identical search trees, as long as they're searched with
+    /// `ponder_samples` (`ponder` stops after a time, so its sample count varies).
+    #[must_use]
+    pub fn with_seed(cache_size_limit: usize, seed: u64) -> Self {
+        Self::with_rng(cache_size_limit, StdRng::seed_from_u64(seed))
+    }
+
+    #[must_use]
+    fn with_rng(cache_size_limit: usize, rng: StdRng) -> Self {
         Self {
-            explored_states: AHashMap::new(),
-            current_ply: 0,
+            explored_states: LruMap::new(),
             cache_size_limit,
+            use_rave: false,
+            rave_equivalence: 1000.0,
+            fpu: DEFAULT_FPU,
+            exploration_rate: DEFAULT_EXPLORATION_RATE,
+            discount: 1.0,
+            tablebase: None,
+            rng,
         }
     }
 
@@ -130,133 +607,517 @@ impl MCTSContext {
 
     /// Clears the explored node cache.
     pub fn clear_cache(&mut self) {
-        self.explored_states = AHashMap::new();
-        self.current_ply = 0;
-    }
-
-    fn prune_explored_states(&mut self) {
-        if self.cache_size() > self.cache_size_limit {
-            let mut last_visit_plies = self
-                .explored_states
-                .values()
-                .map(|state_stats| state_stats.last_visit_ply)
-                .collect_vec();
-            let index = last_visit_plies.len() / 7; // drop the stalest ~14.3%
-            let (_, &mut cutoff_ply, _) = last_visit_plies.select_nth_unstable(index);
-
-            self.explored_states
-                .retain(|_, state_stats| state_stats.last_visit_ply >= cutoff_ply);
-        }
+        self.explored_states = LruMap::new();
     }
 
     /// Performs MCTS iterations on the given game state for the given amount of time.
-    /// Returns the number of iterations/samples performed and the sum of the search depths.
-    pub fn ponder(&mut self, game_state: &GameState, duration: Duration) -> (usize, u32) {
+    /// Returns statistics about the iterations/samples performed.
+    pub fn ponder(&mut self, game_state: &GameState, duration: Duration) -> PonderStats {
         let start_time = Instant::now();
+        self.ponder_while(game_state, |_| start_time.elapsed() < duration)
+    }
 
-        self.current_ply += 1;
-        self.prune_explored_states();
+    /// Performs the given number of MCTS iterations on the given game state. Unlike
+    /// `ponder`, this is reproducible with a seeded context (see `with_seed`).
+    /// Returns statistics about the iterations/samples performed.
+    pub fn ponder_samples(&mut self, game_state: &GameState, num_samples: usize) -> PonderStats {
+        self.ponder_while(game_state, |samples_so_far| samples_so_far < num_samples)
+    }
+
+    /// Performs MCTS iterations on the given game state while `keep_going` (given the
+    /// number of samples so far) returns true.
+    fn ponder_while(
+        &mut self,
+        game_state: &GameState,
+        mut keep_going: impl FnMut(usize) -> bool,
+    ) -> PonderStats {
+        self.explored_states.evict_to(self.cache_size_limit);
+        let initial_cache_size = self.cache_size();
 
-        let mut num_samples = 0;
-        let mut sum_depths = 0;
-        while start_time.elapsed() < duration {
+        let mut stats = PonderStats::default();
+        while keep_going(stats.num_samples) {
             // sample a sequence of moves and update the tree
-            let (_, depth) = self.sample_move(game_state.clone());
-            num_samples += 1;
-            sum_depths += depth;
+            let result =
+                self.sample_move(game_state.clone(), &mut PlayedMoves::default(), MAX_TREE_DEPTH);
+            stats.num_samples += 1;
+            stats.sum_depths += result.depth;
+            stats.max_depth = stats.max_depth.max(result.depth);
+        }
+        stats.new_nodes = self.cache_size() - initial_cache_size;
+        self.log_ponder(game_state, &stats);
+        stats
+    }
+
+    /// Logs a summary of a call to `ponder` at the info level, and the root option
+    /// stats at the debug level.
+    fn log_ponder(&self, game_state: &GameState, ponder_stats: &PonderStats) {
+        if !log::log_enabled!(log::Level::Info) {
+            return;
+        }
+        let state_stats = match self.stats_for(game_state) {
+            Some(state_stats) => state_stats,
+            None => return, // e.g. a forced state
+        };
+
+        let best_option = state_stats.best_option();
+        log::info!(
+            "ponder: {} samples, {} new nodes, {} rollouts at the root; best move {} ({:+.2})",
+            ponder_stats.num_samples,
+            ponder_stats.new_nodes,
+            state_stats.num_rollouts,
+            game_state.valid_moves().nth(best_option).unwrap(),
+            state_stats.options[best_option].expected_score(),
+        );
+        for (hole, option_stats) in game_state.valid_moves().zip_eq(&state_stats.options) {
+            // copy the fields out rather than referencing them
+            let (num_rollouts, proven) = (option_stats.num_rollouts, option_stats.proven);
+            log::debug!(
+                "  move {hole}: {num_rollouts} rollouts, {:+.2} ± {:.2}{}",
+                option_stats.expected_score(),
+                option_stats.score_std_error(),
+                proven.map_or("", |_| " (proven)"),
+            );
         }
-        (num_samples, sum_depths)
     }
 
     /// Returns the cached `StateStats` for a given game state.
+    /// Forced states are never cached (see `StateStats`).
     #[must_use]
     pub fn stats_for(&self, game_state: &GameState) -> Option<&StateStats> {
-        self.explored_states.get(game_state)
+        self.explored_states.peek(&PackedState::from(game_state))
+    }
+
+    /// Returns the cached `StateStats` for a given game state, or `StateStats::forced`
+    /// if it's a (non-terminal) forced state.
+    #[must_use]
+    pub fn stats_or_forced(&self, game_state: &GameState) -> Option<StateStats> {
+        self.stats_for(game_state).cloned().or_else(|| {
+            let is_forced = game_state.valid_moves().count() == 1;
+            (is_forced && game_state.result().is_none()).then(StateStats::forced)
+        })
+    }
+
+    /// Returns up to `max_plies` positions along the principal line from the given state,
+    /// following the first most-visited option at each step. The line ends early at a
+    /// position without stats.
+    #[must_use]
+    pub fn principal_line(&self, game_state: &GameState, max_plies: usize) -> Vec<LineStep> {
+        let mut line = Vec::new();
+        let mut game_state = game_state.clone();
+        let mut stats = self.stats_or_forced(&game_state);
+        while let Some(cur_stats) = stats.filter(|_| line.len() < max_plies) {
+            let option_index = cur_stats.best_option();
+            let hole = game_state.valid_moves().nth(option_index).unwrap();
+            game_state = game_state.after_move(hole);
+            stats = self.stats_or_forced(&game_state);
+            line.push(LineStep {
+                hole,
+                game_state: game_state.clone(),
+                stats: stats.clone(),
+            });
+        }
+        line
+    }
+
+    /// Returns the exact score for Player 1 of the given state if it's covered by the tablebase.
+    #[must_use]
+    fn probe_tablebase(&self, game_state: &GameState) -> Option<Score> {
+        self.tablebase
+            .as_ref()
+            .and_then(|tablebase| tablebase.probe(game_state))
     }
 
     /// Samples a move that a player might make from a state, updating the search tree.
-    /// Returns the rollout score for Player 1.
-    fn sample_move(&mut self, game_state: GameState) -> (i8, u32) {
+    /// Every move made from this state onward is recorded in `played_moves`. The
+    /// sample is cut off after `max_depth` more moves.
+    fn sample_move(
+        &mut self,
+        game_state: GameState,
+        played_moves: &mut PlayedMoves,
+        max_depth: u32,
+    ) -> SampleResult {
         // return the game result if this is a terminal state
         if let Some(score) = game_state.result() {
-            return (score, 0);
+            return SampleResult {
+                score,
+                depth: 0,
+                plies: 0,
+                proven: true,
+            };
         }
 
-        let valid_moves = game_state
-            .valid_moves()
-            .collect::<ArrayVec<_, HOLES_PER_SIDE>>();
+        // likewise if the tablebase covers this state
+        if let Some(score) = self.probe_tablebase(&game_state) {
+            return SampleResult {
+                score,
+                depth: 0,
+                plies: 0,
+                proven: true,
+            };
+        }
+
+        // if the sample has gone on too long (i.e. it's going around a cycle), score
+        // the state as it stands
+        if max_depth == 0 {
+            return SampleResult {
+                score: truncated_score(&game_state),
+                depth: 0,
+                plies: 0,
+                proven: false,
+            };
+        }
+
+        let valid_moves = game_state.valid_moves().collect::<ArrayVec<_, MAX_MOVES>>();
         let num_options = valid_moves.len();
 
         // if there's only one option, immediately continue to the next move (without consulting or
         // updating the search tree)
         if num_options == 1 {
-            let mut game_state = game_state;
-            game_state.make_move(valid_moves[0]);
-            let (score, depth) = self.sample_move(game_state);
-            return (score, depth + 1);
+            let next_state = game_state.after_move(valid_moves[0]);
+            let result = self.sample_move(next_state, played_moves, max_depth - 1);
+            played_moves.insert(game_state.cur_player, valid_moves[0]);
+            return SampleResult {
+                depth: result.depth + 1,
+                plies: result.plies + 1,
+                ..result
+            };
         }
 
         // get which player needs to make a move
         let chooser = game_state.cur_player;
 
-        let update_state_stats =
-            |state_stats: &mut StateStats, option_index: usize, rollout_score: i8| {
-                state_stats.num_rollouts += 1;
-                let option_stats = &mut state_stats.options[option_index];
-                option_stats.num_rollouts += 1;
-                option_stats.total_score += i64::from(match chooser {
-                    Player::Player1 => rollout_score,
-                    Player::Player2 => -rollout_score,
+        // the best score the chooser could possibly get from here: every stone left
+        let p1_lead =
+            Score::from(game_state.p1_state.store) - Score::from(game_state.p2_state.store);
+        let remaining_stones = Score::from(game_state.p1_state.stones_in_holes())
+            + Score::from(game_state.p2_state.stones_in_holes());
+        let best_possible_score = score_for(chooser, p1_lead) + remaining_stones;
+
+        let discount = self.discount;
+        let use_rave = self.use_rave;
+        let update_state_stats = |state_stats: &mut StateStats,
+                                  option_index: usize,
+                                  result: SampleResult,
+                                  played_moves: &PlayedMoves| {
+            let chooser_score = score_for(chooser, result.score);
+            let discounted_score =
+                f64::from(chooser_score) * f64::from(discount).powi(result.plies as i32);
+            state_stats.num_rollouts += 1;
+            let option_stats = &mut state_stats.options[option_index];
+            option_stats.add_rollout(discounted_score);
+            if result.proven {
+                option_stats.proven = Some(chooser_score);
+            }
+
+            if let Some(amaf_options) = &mut state_stats.amaf_options {
+                for (amaf_stats, &hole) in amaf_options.iter_mut().zip(&valid_moves) {
+                    if played_moves.contains(chooser, hole) {
+                        amaf_stats.add_rollout(discounted_score);
+                    }
+                }
+            }
+
+            // the state is proven once all of its options are, or once one of them is
+            // proven to get the best possible score (since a proven win is always picked
+            // from then on, the other options might never be explored)
+            let proven_scores = state_stats
+                .options
+                .iter()
+                .map(|option_stats| option_stats.proven);
+            state_stats.proven = proven_scores
+                .clone()
+                .collect::<Option<ArrayVec<_, MAX_MOVES>>>()
+                .and_then(|proven_scores| proven_scores.into_iter().max())
+                .or_else(|| {
+                    proven_scores
+                        .flatten()
+                        .find(|&proven_score| proven_score == best_possible_score)
                 });
-            };
+        };
 
         // sample an option and the score for Player 1
-        match self.explored_states.entry(game_state.clone()) {
-            Entry::Vacant(entry) => {
-                // this is the first time we've seen this state, so create a new entry
-                let state_stats = entry.insert(StateStats::new(num_options, self.current_ply));
-
-                // at leaf nodes, start by sampling a random option
-                let option_index = thread_rng().gen_range(0..num_options);
+        let key = PackedState::from(&game_state);
+        match self.explored_states.get_mut_with_slot(&key) {
+            None => {
+                // this is the first time we've seen this state; at leaf nodes, start by
+                // sampling a random option
+                let option_index = self.rng.gen_range(0..num_options);
                 let next_move = valid_moves[option_index];
+                let next_state = game_state.after_move(next_move);
+
+                // perform a rollout from this state (or use the exact score if it's known)
+                let (score, rollout_plies, proven) = match self.probe_tablebase(&next_state) {
+                    Some(score) => (score, 0, true),
+                    None => {
+                        let proven = next_state.result().is_some();
+                        let (score, rollout_plies) =
+                            compute_rollout(next_state, played_moves, &mut self.rng);
+                        (score, rollout_plies, proven)
+                    }
+                };
+                played_moves.insert(chooser, next_move);
 
-                // perform a rollout from this state
-                let mut game_state = game_state;
-                game_state.make_move(next_move);
-                let score = compute_rollout_score(game_state);
+                // create a new entry
+                let state_stats = self
+                    .explored_states
+                    .insert(key, StateStats::new(num_options, use_rave));
 
                 // update the stats for this option
-                update_state_stats(state_stats, option_index, score);
+                let result = SampleResult {
+                    score,
+                    depth: 1,
+                    plies: rollout_plies + 1,
+                    proven,
+                };
+                update_state_stats(state_stats, option_index, result, played_moves);
 
-                (score, 1)
+                SampleResult { proven: false, ..result }
             }
-            Entry::Occupied(entry) => {
-                // this state has been seen before; get the stored stats
-                let state_stats = entry.into_mut();
-                state_stats.last_visit_ply = self.current_ply;
+            Some((slot, state_stats)) => {
+                // this state has been seen before; update the stored stats
+                // if the outcome from here is already known, there's nothing left to search
+                if let Some(proven_score) = state_stats.proven {
+                    let score = score_for(chooser, proven_score);
+                    return SampleResult {
+                        score,
+                        depth: 0,
+                        plies: 0,
+                        proven: true,
+                    };
+                }
 
-                // choose an option based on the current stats
-                let (option_index, (_, next_move)) = state_stats
+                // choose an option based on the current stats: play the best option that's
+                // proven to win if there is one, and otherwise never pick one proven to lose
+                let best_proven_win = state_stats
                     .options
                     .iter()
-                    .zip_eq(valid_moves)
                     .enumerate()
-                    .max_by_key(|(_, (option_stats, _))| {
-                        option_stats.puct_score(state_stats.num_rollouts)
+                    .filter_map(|(option_index, option_stats)| {
+                        option_stats
+                            .proven
+                            .filter(|&proven_score| proven_score > 0)
+                            .map(|proven_score| (option_index, proven_score))
                     })
-                    .unwrap();
+                    .max_by_key(|&(_, proven_score)| proven_score)
+                    .map(|(option_index, _)| option_index);
+                let option_index = best_proven_win.unwrap_or_else(|| {
+                    state_stats
+                        .options
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, option_stats)| !option_stats.is_proven_loss())
+                        .max_by_key(|&(option_index, option_stats)| {
+                            match state_stats.amaf_options.as_ref().filter(|_| use_rave) {
+                                Some(amaf_options) => option_stats.puct_rave_score(
+                                    &amaf_options[option_index],
+                                    self.rave_equivalence,
+                                    state_stats.num_rollouts,
+                                    self.exploration_rate,
+                                    self.fpu,
+                                ),
+                                None => option_stats.puct_score(
+                                    state_stats.num_rollouts,
+                                    self.exploration_rate,
+                                    self.fpu,
+                                ),
+                            }
+                        })
+                        .map(|(option_index, _)| option_index)
+                        .expect("a state with unproven options should have a non-losing one")
+                });
+                let next_move = valid_moves[option_index];
 
                 // get the next state and recurse (or return the result if the game ended)
-                let mut game_state2 = game_state.clone();
-                game_state2.make_move(next_move);
-                let (score, depth) = self.sample_move(game_state2);
+                let next_state = game_state.after_move(next_move);
+                let result = self.sample_move(next_state, played_moves, max_depth - 1);
+                let result = SampleResult {
+                    depth: result.depth + 1,
+                    plies: result.plies + 1,
+                    ..result
+                };
+                played_moves.insert(chooser, next_move);
 
-                // update the stats for this option
-                let state_stats = self.explored_states.get_mut(&game_state).unwrap();
-                update_state_stats(state_stats, option_index, score);
+                // update the stats for this option (nothing is evicted while sampling, so
+                // the entry is still in the same slot)
+                let state_stats = self.explored_states.get_slot_mut(slot);
+                update_state_stats(state_stats, option_index, result, played_moves);
 
-                (score, depth + 1)
+                match state_stats.proven {
+                    Some(proven_score) => SampleResult {
+                        score: score_for(chooser, proven_score),
+                        proven: true,
+                        ..result
+                    },
+                    None => SampleResult { proven: false, ..result },
+                }
             }
         }
     }
 }
+
+/// The outcome of sampling a sequence of moves from a state.
+#[derive(Debug, Clone, Copy)]
+struct SampleResult {
+    /// The (rollout) score for Player 1.
+    score: Score,
+
+    /// The number of moves made within the search tree.
+    depth: u32,
+
+    /// The number of moves made until the end of the game (including the rollout),
+    /// or 0 if the score was known exactly before the game ended.
+    plies: u32,
+
+    /// Whether `score` is the exact value of the state under optimal play.
+    proven: bool,
+}
+
+/// Converts a score for Player 1 into a score for the given player.
+#[must_use]
+fn score_for(player: Player, score: Score) -> Score {
+    match player {
+        Player::Player1 => score,
+        Player::Player2 => -score,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state::{GameStateBuilder, SwapOption, SWAP_MOVE};
+
+    /// Returns a position where the player to move wins every remaining stone by
+    /// moving from hole 4 (capturing the opponent's last stones, which ends the game),
+    /// but moving from hole 5 lets the game go on.
+    fn forced_win_state(player: Player) -> GameState {
+        let (own_holes, other_holes) = ([0, 0, 0, 0, 1, 1], [0, 0, 3, 0, 0, 0]);
+        let (p1_holes, p2_holes) = match player {
+            Player::Player1 => (own_holes, other_holes),
+            Player::Player2 => (other_holes, own_holes),
+        };
+        GameStateBuilder::new()
+            .p1_holes(p1_holes)
+            .p1_store(0)
+            .p2_holes(p2_holes)
+            .p2_store(0)
+            .turn(player)
+            .build()
+    }
+
+    #[test]
+    fn search_proves_forced_wins() {
+        for player in [Player::Player1, Player::Player2] {
+            let game_state = forced_win_state(player);
+            let mut mcts_context = MCTSContext::with_seed(10_000, 0);
+            mcts_context.ponder_samples(&game_state, 100);
+
+            let stats = mcts_context.stats_for(&game_state).unwrap();
+            assert_eq!(stats.proven, Some(5), "{player}");
+            assert_eq!(game_state.valid_moves().nth(stats.best_option()), Some(4), "{player}");
+        }
+    }
+
+    #[test]
+    fn the_pie_rule_changes_the_evaluation() {
+        // Player 1 has a lead that Player 2 can't catch up on, unless they take it over
+        let state_with = |swap_option| {
+            GameStateBuilder::new()
+                .p1_holes([1, 0, 0, 0, 0, 1])
+                .p1_store(10)
+                .p2_holes([1, 0, 0, 0, 0, 1])
+                .p2_store(0)
+                .turn(Player::Player2)
+                .swap_option(swap_option)
+                .build()
+        };
+
+        let evaluate = |game_state: &GameState| {
+            let mut mcts_context = MCTSContext::with_seed(10_000, 0);
+            mcts_context.ponder_samples(game_state, 1000);
+            let stats = mcts_context.stats_for(game_state).unwrap();
+            let option_index = stats.best_option_by_score();
+            let best_move = game_state.valid_moves().nth(option_index).unwrap();
+            (best_move, stats.options[option_index].expected_score().into_inner())
+        };
+
+        let (best_move, expected_score) = evaluate(&state_with(SwapOption::Available));
+        assert_eq!(best_move, SWAP_MOVE);
+        assert!(expected_score > 0.0, "{expected_score}");
+
+        let (best_move, expected_score) = evaluate(&state_with(SwapOption::Unavailable));
+        assert_ne!(best_move, SWAP_MOVE);
+        assert!(expected_score < 0.0, "{expected_score}");
+    }
+
+    #[test]
+    fn contexts_with_the_same_seed_build_the_same_tree() {
+        let game_state = GameState::default();
+        let [first, second] = [(); 2].map(|_| {
+            let mut mcts_context = MCTSContext::with_seed(100_000, 7);
+            mcts_context.ponder_samples(&game_state, 2000);
+            mcts_context
+        });
+
+        assert_eq!(first.cache_size(), second.cache_size());
+        let states = iter::once(game_state.clone())
+            .chain(game_state.successors().map(|(_, next_state)| next_state));
+        for state in states {
+            assert_eq!(
+                format!("{:?}", first.stats_for(&state)),
+                format!("{:?}", second.stats_for(&state)),
+            );
+        }
+        assert_eq!(
+            format!("{:?}", first.principal_line(&game_state, 20)),
+            format!("{:?}", second.principal_line(&game_state, 20)),
+        );
+    }
+
+    #[test]
+    fn a_discount_of_one_leaves_scores_undiscounted() {
+        // the winning move ends the game right away, so its score is discounted once
+        let game_state = forced_win_state(Player::Player1);
+        for (discount, expected_score) in [(1.0, 5.0), (0.5, 2.5)] {
+            let mut mcts_context = MCTSContext::with_seed(10_000, 0);
+            mcts_context.discount = discount;
+            mcts_context.ponder_samples(&game_state, 100);
+
+            let stats = mcts_context.stats_for(&game_state).unwrap();
+            assert_eq!(stats.options[0].expected_score().into_inner(), expected_score);
+        }
+
+        // undiscounted, every option's total is a sum of whole rollout scores
+        let game_state = GameState::default();
+        let mut mcts_context = MCTSContext::with_seed(10_000, 0);
+        mcts_context.discount = 1.0;
+        mcts_context.ponder_samples(&game_state, 1000);
+        for option_stats in &mcts_context.stats_for(&game_state).unwrap().options {
+            let total_score = option_stats.total_score;
+            assert_eq!(total_score, total_score.round());
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn stats_survive_a_serde_round_trip() {
+        let game_state = GameState::default();
+        let mut mcts_context = MCTSContext::with_seed(10_000, 0);
+        mcts_context.ponder_samples(&game_state, 1000);
+        let stats = mcts_context.stats_for(&game_state).unwrap();
+
+        let json = serde_json::to_string(stats).unwrap();
+        let round_tripped: StateStats = serde_json::from_str(&json).unwrap();
+        assert_eq!(format!("{round_tripped:?}"), format!("{stats:?}"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn option_stats_from_before_the_spread_and_outcome_counts_load() {
+        let option: OptionStats =
+            serde_json::from_str(r#"{"num_rollouts":4,"total_score":10.0,"proven":null}"#).unwrap();
+        let (num_rollouts, total_score) = (option.num_rollouts, option.total_score);
+        let (num_wins, num_draws) = (option.num_wins, option.num_draws);
+        assert_eq!((num_rollouts, total_score), (4, 10.0));
+        assert_eq!((num_wins, num_draws), (0, 0));
+    }
+}
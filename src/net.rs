@@ -0,0 +1,263 @@
+//! Playing a game between two machines over TCP. One instance hosts the game and
+//! plays Player 1; the other joins it and plays Player 2. Each message is sent as a
+//! single line of JSON.
+
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{Shutdown, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use egui::{mutex::Mutex, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::game_state::{GameState, Player};
+
+/// The default address to host or join a game at.
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:7878";
+
+/// How often the host checks for an incoming connection (or for being dropped).
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A message between the two instances.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum NetMessage {
+    /// Sent by the host once connected (and only then): the state to start playing from.
+    Start(GameState),
+
+    /// The sender made a move, resulting in the given state. The receiver should
+    /// check that it reaches the same state.
+    Move {
+        hole: usize,
+        resulting_state: GameState,
+    },
+}
+
+/// Data shared between a `NetConnection` and its thread.
+#[derive(Default)]
+struct SharedData {
+    /// The stream to send messages on, once connected.
+    stream: Option<TcpStream>,
+
+    /// A description of the error that ended the connection, if any.
+    error: Option<String>,
+}
+
+/// Manages a connection to another instance and the thread receiving its messages.
+pub struct NetConnection {
+    /// The player controlled by this instance.
+    local_player: Player,
+
+    /// The messages received from the other instance.
+    receiver: Receiver<NetMessage>,
+
+    /// The data shared with the connection thread.
+    shared: Arc<Mutex<SharedData>>,
+
+    /// Set when this connection is dropped, to stop waiting for the other instance.
+    stopped: Arc<AtomicBool>,
+
+    /// Whether the host's `Start` message has been accepted (when joining).
+    received_start: bool,
+}
+
+impl NetConnection {
+    /// Starts hosting a game at the given address. Once the other instance joins,
+    /// play starts from `game_state`, which is sent to it as a `Start` message.
+    pub fn host(address: &str, game_state: GameState, ctx: &Context) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+
+        let (connection, sender) = NetConnection::new(Player::Player1);
+        let shared = connection.shared.clone();
+        let stopped = connection.stopped.clone();
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let stream = loop {
+                if stopped.load(Ordering::Relaxed) {
+                    return;
+                }
+                match listener.accept() {
+                    Ok((stream, _)) => break stream,
+                    Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                        thread::sleep(ACCEPT_POLL_INTERVAL);
+                    }
+                    Err(err) => return fail(&shared, &ctx, format!("Failed to connect: {err}")),
+                }
+            };
+
+            let start = NetMessage::Start(game_state.clone());
+            let result = stream
+                .set_nonblocking(false)
+                .and_then(|()| connected(&stream, &shared))
+                .and_then(|()| send_message(&shared, &start));
+            if let Err(err) = result {
+                return fail(&shared, &ctx, format!("Failed to connect: {err}"));
+            }
+            receive_messages(stream, &sender, &shared, &ctx);
+        });
+
+        Ok(connection)
+    }
+
+    /// Joins the game hosted at the given address. The host's starting state is
+    /// received as a `Start` message.
+    #[must_use]
+    pub fn join(address: &str, ctx: &Context) -> Self {
+        let (connection, sender) = NetConnection::new(Player::Player2);
+        let shared = connection.shared.clone();
+        let stopped = connection.stopped.clone();
+        let address = address.to_string();
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let result = TcpStream::connect(address)
+                .and_then(|stream| connected(&stream, &shared).map(|()| stream));
+            match result {
+                Ok(_) if stopped.load(Ordering::Relaxed) => {}
+                Ok(stream) => receive_messages(stream, &sender, &shared, &ctx),
+                Err(err) => fail(&shared, &ctx, format!("Failed to connect: {err}")),
+            }
+        });
+
+        connection
+    }
+
+    /// Creates a connection that isn't connected yet, along with the sender for
+    /// its received messages.
+    #[must_use]
+    fn new(local_player: Player) -> (Self, Sender<NetMessage>) {
+        let (sender, receiver) = mpsc::channel();
+        let connection = NetConnection {
+            local_player,
+            receiver,
+            shared: Arc::new(Mutex::new(SharedData::default())),
+            stopped: Arc::new(AtomicBool::new(false)),
+            received_start: false,
+        };
+        (connection, sender)
+    }
+
+    /// Returns the player controlled by this instance.
+    #[must_use]
+    pub fn local_player(&self) -> Player {
+        self.local_player
+    }
+
+    /// Returns whether the other instance has connected (and the connection hasn't
+    /// failed since).
+    #[must_use]
+    pub fn is_connected(&self) -> bool {
+        let shared = self.shared.lock();
+        shared.stream.is_some() && shared.error.is_none()
+    }
+
+    /// Returns whether play can start: the other instance has connected, and (when
+    /// joining) the host's `Start` message has been accepted.
+    #[must_use]
+    pub fn is_ready(&self) -> bool {
+        self.is_connected() && (self.local_player == Player::Player1 || self.received_start)
+    }
+
+    /// Returns whether a `Start` message may be accepted now: only when joining, and
+    /// only once (which is before any moves, since those wait for it).
+    #[must_use]
+    pub fn expects_start(&self) -> bool {
+        self.local_player == Player::Player2 && !self.received_start
+    }
+
+    /// Records that the host's `Start` message has been accepted.
+    pub fn accept_start(&mut self) {
+        self.received_start = true;
+    }
+
+    /// Returns a description of the error that ended the connection, if any.
+    #[must_use]
+    pub fn error(&self) -> Option<String> {
+        self.shared.lock().error.clone()
+    }
+
+    /// Ends the connection with the given error (e.g. after receiving an invalid move).
+    pub fn set_error(&self, error: String) {
+        self.shared.lock().error.get_or_insert(error);
+    }
+
+    /// Returns the next message received from the other instance, if there is one.
+    #[must_use]
+    pub fn try_recv(&self) -> Option<NetMessage> {
+        self.receiver.try_recv().ok()
+    }
+
+    /// Tells the other instance about a move made by the local player.
+    pub fn send_move(&self, hole: usize, resulting_state: GameState) {
+        let message = NetMessage::Move { hole, resulting_state };
+        if let Err(err) = send_message(&self.shared, &message) {
+            self.set_error(format!("Failed to send move: {err}"));
+        }
+    }
+}
+
+impl Drop for NetConnection {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        if let Some(stream) = &self.shared.lock().stream {
+            // unblock the connection thread; it may already be disconnected
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+/// Stores a copy of a newly connected stream for sending messages.
+fn connected(stream: &TcpStream, shared: &Mutex<SharedData>) -> io::Result<()> {
+    shared.lock().stream = Some(stream.try_clone()?);
+    Ok(())
+}
+
+/// Sends a message on the connection's stream. Does nothing if not connected yet.
+fn send_message(shared: &Mutex<SharedData>, message: &NetMessage) -> io::Result<()> {
+    let mut shared = shared.lock();
+    if let Some(stream) = &mut shared.stream {
+        let mut line = serde_json::to_vec(message)?;
+        line.push(b'\n');
+        stream.write_all(&line)?;
+    }
+    Ok(())
+}
+
+/// Forwards messages from the stream to `sender` until the connection ends.
+fn receive_messages(
+    stream: TcpStream,
+    sender: &Sender<NetMessage>,
+    shared: &Mutex<SharedData>,
+    ctx: &Context,
+) {
+    for line in BufReader::new(stream).lines() {
+        let message = line
+            .map_err(|err| format!("Connection lost: {err}"))
+            .and_then(|line| {
+                serde_json::from_str(&line)
+                    .map_err(|err| format!("Received an invalid message: {err}"))
+            });
+        match message {
+            Ok(message) => {
+                if sender.send(message).is_err() {
+                    return; // the connection was dropped
+                }
+                ctx.request_repaint();
+            }
+            Err(error) => return fail(shared, ctx, error),
+        }
+    }
+    fail(shared, ctx, "The opponent disconnected".to_string());
+}
+
+/// Records the error that ended the connection.
+fn fail(shared: &Mutex<SharedData>, ctx: &Context, error: String) {
+    shared.lock().error.get_or_insert(error);
+    ctx.request_repaint();
+}
@@ -0,0 +1,118 @@
+use std::{
+    collections::VecDeque,
+    fs,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    time::Duration,
+};
+
+use ahash::AHashMap;
+
+use crate::{
+    game_state::{GameState, PackedState},
+    mcts::MCTSContext,
+};
+
+/// The default file that the opening book is loaded from and saved to.
+pub const DEFAULT_BOOK_PATH: &str = "opening_book.txt";
+
+/// A precomputed mapping from early-game states to the best move in each.
+#[derive(Debug, Clone, Default)]
+pub struct OpeningBook {
+    moves: AHashMap<PackedState, usize>,
+}
+
+impl OpeningBook {
+    /// Returns the book move for the given state, if there is one.
+    #[must_use]
+    pub fn get(&self, game_state: &GameState) -> Option<usize> {
+        self.moves.get(&PackedState::from(game_state)).copied()
+    }
+
+    /// Returns the number of states in the book.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    /// Returns whether the book is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+
+    /// Generates a book by searching each state reachable within `max_plies` moves of
+    /// the start position (breadth-first) for `ponder_time`, stopping once the book
+    /// has `max_entries` states.
+    #[must_use]
+    pub fn generate(max_plies: u32, ponder_time: Duration, max_entries: usize) -> Self {
+        let mut book = OpeningBook::default();
+        let mut mcts_context = MCTSContext::new(20_000_000);
+
+        let mut queue = VecDeque::from([(GameState::default(), 0)]);
+        while let Some((game_state, ply)) = queue.pop_front() {
+            if book.len() >= max_entries {
+                break;
+            }
+            if game_state.result().is_some() || book.get(&game_state).is_some() {
+                continue;
+            }
+
+            // states with a single valid move don't need searching
+            if game_state.valid_moves().nth(1).is_some() {
+                mcts_context.ponder(&game_state, ponder_time);
+                let stats = mcts_context
+                    .stats_for(&game_state)
+                    .expect("pondering should have expanded the state");
+                let option_index = stats.best_option();
+                let best_move = game_state.valid_moves().nth(option_index).unwrap();
+                book.moves.insert(PackedState::from(&game_state), best_move);
+            }
+
+            if ply < max_plies {
+                queue.extend(
+                    game_state
+                        .successors()
+                        .map(|(_, next_state)| (next_state, ply + 1)),
+                );
+            }
+        }
+
+        book
+    }
+
+    /// Loads a book from the given file. Each line holds a packed state (in hex)
+    /// and the book move for it, which must be legal in that state.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let invalid_line = |line: &str| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("invalid book line: {line:?}"))
+        };
+
+        let mut book = OpeningBook::default();
+        for line in BufReader::new(fs::File::open(path)?).lines() {
+            let line = line?;
+            let (state_bits, hole) = line.split_once(' ').ok_or_else(|| invalid_line(&line))?;
+            let state_bits =
+                u128::from_str_radix(state_bits, 16).map_err(|_| invalid_line(&line))?;
+            let hole = hole.parse().map_err(|_| invalid_line(&line))?;
+
+            // a book move that isn't legal (e.g. from a book saved by an older version)
+            // would never be played, leaving the bot stuck
+            let packed_state = PackedState::from_bits(state_bits);
+            if !GameState::from(packed_state).is_legal_move(hole) {
+                return Err(invalid_line(&line));
+            }
+            book.moves.insert(packed_state, hole);
+        }
+        Ok(book)
+    }
+
+    /// Saves the book to the given file, in the format read by `load`.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(fs::File::create(path)?);
+        for (packed_state, hole) in &self.moves {
+            writeln!(writer, "{:032x} {hole}", packed_state.to_bits())?;
+        }
+        writer.flush()
+    }
+}
@@ -0,0 +1,147 @@
+use std::fmt;
+
+use crate::game_state::{GameState, MAX_MOVES};
+
+/// The width of the longest bar in a `BranchingStats` histogram, in characters.
+const HISTOGRAM_WIDTH: u64 = 40;
+
+/// Counts the number of leaf states reachable from the given state within
+/// `depth` plies. A ply ends when the turn passes to the other player, so a
+/// move that earns an extra turn doesn't use up any depth. Terminal states
+/// reached early are counted as leaves.
+#[must_use]
+pub fn perft(game_state: &GameState, depth: u32) -> u64 {
+    if depth == 0 || game_state.result().is_some() {
+        return 1;
+    }
+
+    game_state
+        .successors()
+        .map(|(_, next_state)| {
+            if next_state.cur_player == game_state.cur_player {
+                // extra turn; the same player moves again within this ply
+                perft(&next_state, depth)
+            } else {
+                perft(&next_state, depth - 1)
+            }
+        })
+        .sum()
+}
+
+/// Statistics about the number of valid moves at each decision point of a game tree.
+#[derive(Debug, Clone, Default)]
+pub struct BranchingStats {
+    /// The number of decision points (non-terminal states) visited.
+    pub num_decisions: u64,
+
+    /// The total number of valid moves over all decision points.
+    pub total_moves: u64,
+
+    /// The number of decision points with each number of valid moves.
+    pub histogram: [u64; MAX_MOVES + 1],
+}
+
+impl BranchingStats {
+    /// Returns the average number of valid moves per decision point.
+    #[must_use]
+    pub fn average(&self) -> f64 {
+        self.total_moves as f64 / self.num_decisions as f64
+    }
+
+    /// Returns the fewest valid moves at any decision point, or None if there were none.
+    #[must_use]
+    pub fn min(&self) -> Option<usize> {
+        self.histogram.iter().position(|&count| count > 0)
+    }
+
+    /// Returns the most valid moves at any decision point, or None if there were none.
+    #[must_use]
+    pub fn max(&self) -> Option<usize> {
+        self.histogram.iter().rposition(|&count| count > 0)
+    }
+}
+
+impl fmt::Display for BranchingStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (min, max) = match (self.min(), self.max()) {
+            (Some(min), Some(max)) => (min, max),
+            _ => return write!(f, "no decision points"),
+        };
+        writeln!(
+            f,
+            "{} decision points: {:.2} moves on average (min {min}, max {max})",
+            self.num_decisions,
+            self.average(),
+        )?;
+        let max_count = self.histogram.iter().copied().max().unwrap_or(0).max(1);
+        for (num_moves, &count) in self.histogram.iter().enumerate().skip(1) {
+            let bar_len = (count * HISTOGRAM_WIDTH).div_ceil(max_count);
+            writeln!(f, "{num_moves}: {} {count}", "#".repeat(bar_len as usize))?;
+        }
+        Ok(())
+    }
+}
+
+/// Walks the same tree as `perft` and collects the number of valid moves at each
+/// decision point, including each decision in a chain of extra turns.
+#[must_use]
+pub fn branching_stats(game_state: &GameState, depth: u32) -> BranchingStats {
+    let mut stats = BranchingStats::default();
+    add_branching_stats(game_state, depth, &mut stats);
+    stats
+}
+
+fn add_branching_stats(game_state: &GameState, depth: u32, stats: &mut BranchingStats) {
+    if depth == 0 || game_state.result().is_some() {
+        return;
+    }
+
+    let mut num_moves = 0;
+    for (_, next_state) in game_state.successors() {
+        num_moves += 1;
+        if next_state.cur_player == game_state.cur_player {
+            // extra turn; the same player moves again within this ply
+            add_branching_stats(&next_state, depth, stats);
+        } else {
+            add_branching_stats(&next_state, depth - 1, stats);
+        }
+    }
+    stats.num_decisions += 1;
+    stats.total_moves += num_moves as u64;
+    stats.histogram[num_moves] += 1;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state::{GameStateBuilder, HOLES_PER_SIDE};
+
+    #[test]
+    fn perft_from_the_start_position() {
+        let game_state = GameState::default();
+        let counts = (1..=4)
+            .map(|depth| perft(&game_state, depth))
+            .collect::<Vec<_>>();
+        assert_eq!(counts, [10, 116, 1022, 9682]);
+    }
+
+    #[test]
+    fn extra_turns_dont_use_up_depth() {
+        // moving from hole 0 starts a chain of extra turns (holes 0, 1, 0) that ends the
+        // game, and moving from hole 1 earns one, after which Player 1's only move sows
+        // into Player 2's side; each line is a single ply
+        let game_state = GameStateBuilder::new()
+            .p1_holes([1, 2, 0, 0, 0, 0])
+            .p2_holes([0, 0, 0, 0, 1, 1])
+            .build();
+        assert_eq!(perft(&game_state, 1), 2);
+    }
+
+    #[test]
+    fn terminal_states_are_leaves() {
+        let game_state = GameStateBuilder::new()
+            .p1_holes([0; HOLES_PER_SIDE])
+            .build();
+        assert_eq!(perft(&game_state, 3), 1);
+    }
+}
@@ -0,0 +1,242 @@
+use std::time::{Duration, Instant};
+
+use itertools::Itertools;
+use rand::{seq::IteratorRandom, thread_rng};
+
+use crate::{
+    game_state::{GameOutcome, GameState, Player},
+    mcts::{sample_move_with_temperature, MCTSContext, DEFAULT_CACHE_SIZE_LIMIT},
+};
+
+/// How long each engine ponders between checks of its move budget.
+const PONDER_CHUNK: Duration = Duration::from_millis(10);
+
+/// The settings for one engine in a self-play match.
+#[derive(Debug, Clone, Copy)]
+pub struct EngineConfig {
+    pub use_rave: bool,
+    pub rave_equivalence: f32,
+    pub fpu: f32,
+    pub exploration_rate: f32,
+    pub discount: f32,
+
+    /// The temperature for picking a move from the search statistics (see
+    /// `sample_move_with_temperature`), or 0 to always play the best one.
+    pub temperature: f32,
+
+    /// The maximum time to think about each move.
+    pub move_time: Duration,
+
+    /// The number of rollouts at the current state after which the engine stops
+    /// thinking and moves, if any.
+    pub samples_per_move: Option<u32>,
+
+    pub cache_size_limit: usize,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        let context = MCTSContext::new(DEFAULT_CACHE_SIZE_LIMIT);
+        Self {
+            use_rave: context.use_rave,
+            rave_equivalence: context.rave_equivalence,
+            fpu: context.fpu,
+            exploration_rate: context.exploration_rate,
+            discount: context.discount,
+            temperature: 0.0,
+            move_time: Duration::from_millis(100),
+            samples_per_move: None,
+            cache_size_limit: context.cache_size_limit,
+        }
+    }
+}
+
+impl EngineConfig {
+    /// Returns a fresh `MCTSContext` with these settings.
+    #[must_use]
+    fn new_context(&self) -> MCTSContext {
+        let mut context = MCTSContext::new(self.cache_size_limit);
+        context.use_rave = self.use_rave;
+        context.rave_equivalence = self.rave_equivalence;
+        context.fpu = self.fpu;
+        context.exploration_rate = self.exploration_rate;
+        context.discount = self.discount;
+        context
+    }
+
+    /// Thinks about the given (non-terminal) state and returns the move to make.
+    #[must_use]
+    fn choose_move(&self, context: &mut MCTSContext, game_state: &GameState) -> usize {
+        if let Ok(hole) = game_state.valid_moves().exactly_one() {
+            return hole;
+        }
+
+        let start_time = Instant::now();
+        loop {
+            context.ponder(game_state, PONDER_CHUNK);
+            let budget_reached = self.samples_per_move.is_some_and(|budget| {
+                context
+                    .stats_for(game_state)
+                    .is_some_and(|stats| stats.num_rollouts >= budget)
+            });
+            if budget_reached || start_time.elapsed() >= self.move_time {
+                break;
+            }
+        }
+
+        match context.stats_for(game_state) {
+            Some(stats) => {
+                let index = if self.temperature > 0.0 {
+                    sample_move_with_temperature(stats, self.temperature, &mut thread_rng())
+                } else {
+                    stats.best_option()
+                };
+                game_state.valid_moves().nth(index).unwrap()
+            }
+            // the search never expanded this state (e.g. it was solved by a tablebase)
+            None => game_state.valid_moves().choose(&mut thread_rng()).unwrap(),
+        }
+    }
+}
+
+/// The record of engine A against engine B in a match.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MatchResults {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+
+    /// The sum of engine A's final score margins (negative for losses).
+    pub total_margin: i64,
+}
+
+impl MatchResults {
+    #[must_use]
+    pub fn num_games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Returns engine A's average score per game (1 for a win, 0.5 for a draw).
+    #[must_use]
+    pub fn score(&self) -> f64 {
+        if self.num_games() == 0 {
+            return 0.5;
+        }
+        (f64::from(self.wins) + 0.5 * f64::from(self.draws)) / f64::from(self.num_games())
+    }
+
+    /// Returns engine A's average final score margin per game.
+    #[must_use]
+    pub fn average_margin(&self) -> f64 {
+        if self.num_games() == 0 {
+            return 0.0;
+        }
+        self.total_margin as f64 / f64::from(self.num_games())
+    }
+
+    /// Returns the standard error of `score`.
+    #[must_use]
+    pub fn score_std_error(&self) -> f64 {
+        let num_games = f64::from(self.num_games());
+        if num_games < 2.0 {
+            return f64::INFINITY;
+        }
+        let score = self.score();
+        let variance = (f64::from(self.wins) * (1.0 - score).powi(2)
+            + f64::from(self.draws) * (0.5 - score).powi(2)
+            + f64::from(self.losses) * score.powi(2))
+            / num_games;
+        (variance / num_games).sqrt()
+    }
+
+    /// Returns how many standard errors `score` is from an even match. Beyond about
+    /// ±2, the difference between the engines is significant at the 95% level.
+    #[must_use]
+    pub fn z_score(&self) -> f64 {
+        (self.score() - 0.5) / self.score_std_error()
+    }
+
+    /// Returns the Elo rating difference (A - B) implied by `score`.
+    #[must_use]
+    pub fn elo_difference(&self) -> f64 {
+        let score = self.score().clamp(0.001, 0.999);
+        -400.0 * (1.0 / score - 1.0).log10()
+    }
+
+    /// Returns a single-line, machine-readable summary of the results.
+    #[must_use]
+    pub fn summary_line(&self) -> String {
+        format!(
+            "match games={} wins={} draws={} losses={} score={:.3} margin={:+.2} elo={:+.0} \
+             z={:.2}",
+            self.num_games(),
+            self.wins,
+            self.draws,
+            self.losses,
+            self.score(),
+            self.average_margin(),
+            self.elo_difference(),
+            self.z_score(),
+        )
+    }
+}
+
+/// Plays a single game between two engines from the start position, and returns
+/// the outcome. The game stops early once it's decided (see `GameState::is_decided`),
+/// in which case it's scored as if it ended there (see `GameState::score_if_ended`).
+#[must_use]
+pub fn play_game(player1: &EngineConfig, player2: &EngineConfig) -> GameOutcome {
+    let mut p1_context = player1.new_context();
+    let mut p2_context = player2.new_context();
+
+    let mut game_state = GameState::default();
+    loop {
+        if let Some(outcome) = game_state.outcome() {
+            return outcome;
+        }
+        if game_state.is_decided().is_some() {
+            // the winner can't change, but the margin still counts the stones left
+            return GameOutcome::from_score(game_state.score_if_ended());
+        }
+        let hole = match game_state.cur_player {
+            Player::Player1 => player1.choose_move(&mut p1_context, &game_state),
+            Player::Player2 => player2.choose_move(&mut p2_context, &game_state),
+        };
+        game_state.make_move(hole);
+    }
+}
+
+/// Plays `num_games` games between engines A and B, alternating who moves first,
+/// and returns A's record. `on_game_finished` is called with the record so far
+/// after each game.
+pub fn run_match(
+    engine_a: &EngineConfig,
+    engine_b: &EngineConfig,
+    num_games: u32,
+    mut on_game_finished: impl FnMut(&MatchResults),
+) -> MatchResults {
+    let mut results = MatchResults::default();
+    for game_index in 0..num_games {
+        let a_player = if game_index.is_multiple_of(2) {
+            Player::Player1
+        } else {
+            Player::Player2
+        };
+        let outcome = match a_player {
+            Player::Player1 => play_game(engine_a, engine_b),
+            Player::Player2 => play_game(engine_b, engine_a),
+        };
+        match outcome.winner() {
+            None => results.draws += 1,
+            Some(winner) if winner == a_player => results.wins += 1,
+            Some(_) => results.losses += 1,
+        }
+        let p1_margin = i64::from(outcome.score());
+        results.total_margin += match a_player {
+            Player::Player1 => p1_margin,
+            Player::Player2 => -p1_margin,
+        };
+        on_game_finished(&results);
+    }
+    results
+}
@@ -0,0 +1,152 @@
+use crate::game_state::{GameState, Player, Rules, Score, SwapOption};
+
+/// The default maximum number of stones in holes for the tablebase used by the search.
+pub const DEFAULT_MAX_STONES: u8 = 10;
+
+/// The largest maximum number of stones in holes that's practical to build on demand
+/// (a few million positions, in seconds); each extra stone multiplies the work by
+/// about four.
+pub const PRACTICAL_MAX_STONES: u8 = 12;
+
+/// A marker for table entries that haven't been computed yet.
+const UNKNOWN: Score = Score::MIN;
+
+/// A table of exact values for every position with at most a given number of
+/// stones in holes, computed by exhaustively working back from the terminal
+/// positions.
+///
+/// Since the stores never affect which moves are possible, a position's value
+/// is stored as the net number of stones Player 1 will gain from here on
+/// (relative to Player 2) under optimal play, indexed by `GameState::rank`.
+pub struct Tablebase {
+    max_stones: u8,
+
+    /// The rules the positions are solved under; other positions aren't covered.
+    rules: Rules,
+
+    /// The table for each number of stones in holes, in `0..=max_stones`.
+    values: Vec<Vec<Score>>,
+}
+
+impl Tablebase {
+    /// Computes the tablebase for all positions with at most `max_stones` stones in holes,
+    /// under the default rules.
+    #[must_use]
+    pub fn new(max_stones: u8) -> Self {
+        let mut tablebase = Tablebase {
+            max_stones,
+            rules: Rules::default(),
+            values: (0..=max_stones)
+                .map(|stones| {
+                    let num_ranks = GameState::num_ranks(stones);
+                    vec![UNKNOWN; usize::try_from(num_ranks).expect("tablebase is too large")]
+                })
+                .collect(),
+        };
+
+        // moves never add stones to the holes, so filling in positions with fewer stones
+        // first keeps the recursion shallow
+        for stones in 0..=max_stones {
+            for rank in 0..GameState::num_ranks(stones) {
+                tablebase.solve(stones, rank);
+            }
+        }
+        tablebase
+    }
+
+    /// Returns the maximum number of stones in holes covered by this tablebase.
+    #[must_use]
+    pub fn max_stones(&self) -> u8 {
+        self.max_stones
+    }
+
+    /// Returns the exact final score (for Player 1) of the given state under optimal
+    /// play, or None if it has too many stones in holes (or different rules) to be covered.
+    /// Positions where the swap is still to come aren't covered either, since the table
+    /// is solved without it.
+    #[must_use]
+    pub fn probe(&self, game_state: &GameState) -> Option<Score> {
+        let stones = stones_in_holes(game_state);
+        if stones > self.max_stones
+            || game_state.rules != self.rules
+            || game_state.swap_option != SwapOption::Unavailable
+        {
+            return None;
+        }
+        let value = self.values[usize::from(stones)][game_state.rank() as usize];
+        debug_assert_ne!(value, UNKNOWN, "tablebase entry wasn't computed");
+        Some(store_difference(game_state) + value)
+    }
+
+    /// Computes (if necessary) and returns the value of the given position.
+    fn solve(&mut self, stones: u8, rank: u64) -> Score {
+        let value = self.values[usize::from(stones)][rank as usize];
+        if value != UNKNOWN {
+            return value;
+        }
+
+        let mut game_state = GameState::unrank(stones, rank);
+        game_state.rules = self.rules;
+        let value = if let Some(score) = game_state.result() {
+            // the stores are empty, so the final score is just what's left in the holes
+            score
+        } else {
+            let child_values = game_state
+                .successors()
+                .map(|(_, mut next_state)| {
+                    // split the child's value into what was just put into the stores and
+                    // the (store-independent) value of the rest of the game
+                    let gained = store_difference(&next_state);
+                    next_state.p1_state.store = 0;
+                    next_state.p2_state.store = 0;
+                    gained + self.solve(stones_in_holes(&next_state), next_state.rank())
+                })
+                .collect::<Vec<_>>();
+            match game_state.cur_player {
+                Player::Player1 => child_values.into_iter().max(),
+                Player::Player2 => child_values.into_iter().min(),
+            }
+            .expect("a non-terminal state should have a valid move")
+        };
+
+        self.values[usize::from(stones)][rank as usize] = value;
+        value
+    }
+}
+
+/// Returns the total number of stones in all holes on the board.
+fn stones_in_holes(game_state: &GameState) -> u8 {
+    game_state.p1_state.stones_in_holes() + game_state.p2_state.stones_in_holes()
+}
+
+/// Returns Player 1's store minus Player 2's store.
+fn store_difference(game_state: &GameState) -> Score {
+    Score::from(game_state.p1_state.store) - Score::from(game_state.p2_state.store)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state::GameStateBuilder;
+
+    #[test]
+    fn positions_before_the_swap_arent_covered() {
+        let tablebase = Tablebase::new(4);
+        let state_with = |swap_option| {
+            GameStateBuilder::new()
+                .p1_holes([0, 0, 0, 0, 1, 1])
+                .p1_store(10)
+                .p2_holes([1, 0, 0, 0, 0, 1])
+                .p2_store(0)
+                .turn(Player::Player2)
+                .swap_option(swap_option)
+                .build()
+        };
+
+        assert!(tablebase
+            .probe(&state_with(SwapOption::Unavailable))
+            .is_some());
+        assert_eq!(tablebase.probe(&state_with(SwapOption::Pending)), None);
+        assert_eq!(tablebase.probe(&state_with(SwapOption::Available)), None);
+    }
+}
@@ -1,4 +1,7 @@
 use std::{
+    cell::Cell,
+    collections::VecDeque,
+    panic::{self, AssertUnwindSafe},
     sync::{
         mpsc::{self, Sender},
         Arc,
@@ -11,9 +14,26 @@ use egui::{mutex::Mutex, Context};
 
 use crate::{
     game_state::GameState,
-    mcts::{MCTSContext, StateStats},
+    mcts::{LineStep, MCTSContext, StateStats, DEFAULT_EXPLORATION_RATE},
+    tablebase::{self, Tablebase},
 };
 
+/// The default rate at which the worker publishes its results.
+pub const DEFAULT_UPDATE_HZ: f32 = 60.0;
+
+/// How long the worker searches (or sleeps, when there's nothing to search) between
+/// checks for messages. Results are published separately, at `WorkerData::update_hz`.
+const WORK_SLICE: Duration = Duration::from_millis(10);
+
+/// How often the worker records the expected score of each option at the active state.
+const SCORE_HISTORY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The maximum number of recorded expected scores for each option.
+pub const SCORE_HISTORY_LEN: usize = 100;
+
+/// The maximum number of recorded sample rates (which are measured about once a second).
+const SPS_HISTORY_LEN: usize = 30;
+
 /// A message from the main thread to the worker thread.
 enum Message {
     /// Stop the worker thread.
@@ -24,6 +44,21 @@ enum Message {
 
     /// Set the active game state to work on.
     SetActiveState(GameState),
+
+    /// Set the active game state, and stop searching it after the given time.
+    SearchFor(GameState, Duration),
+
+    /// Look up the cached stats for a game state, and publish them in
+    /// `WorkerData::queried_stats`.
+    QueryStats(GameState),
+
+    /// Set additional game states (e.g. anticipated future positions) to split
+    /// effort with the active state.
+    SetPonderStates(Vec<GameState>),
+
+    /// Rebuild the tablebase to cover positions with up to the given number of
+    /// stones in holes.
+    SetTablebaseStones(u8),
 }
 
 /// Data representing the state of the worker thread's computation and results
@@ -32,6 +67,19 @@ enum Message {
 pub struct WorkerStateData {
     pub game_state: GameState,
     pub stats: StateStats,
+
+    /// The principal line from the active state, up to `WorkerData::line_plies` long.
+    pub line: Vec<LineStep>,
+
+    /// The recent expected scores of each option at the active state (oldest first),
+    /// recorded periodically since it became the active state. Shared between updates,
+    /// and only copied when a new reading is recorded.
+    pub score_history: Arc<Vec<VecDeque<f32>>>,
+
+    /// The cached stats for the state after each option at the active state (in the
+    /// same order as `stats.options`), if it has been explored. Empty unless
+    /// `WorkerData::publish_child_stats` is set.
+    pub child_stats: Vec<Option<StateStats>>,
 }
 
 /// Shared data on the overall state of the worker thread.
@@ -39,8 +87,54 @@ pub struct WorkerStateData {
 pub struct WorkerData {
     pub cache_size: usize,
     pub cache_size_limit: usize,
+    pub use_rave: bool,
+
+    /// The per-ply discount applied to rollout scores (see `MCTSContext::discount`).
+    pub discount: f32,
+
+    /// The search's exploration constant (see `MCTSContext::exploration_rate`).
+    pub exploration_rate: f32,
+
+    /// The maximum number of stones in holes for which the worker's tablebase gives
+    /// exact values, rather than searching.
+    pub tablebase_stones: u8,
+
+    /// The number of rollouts at the active state after which the worker stops
+    /// searching it, if any.
+    pub rollout_budget: Option<u32>,
+
+    /// How many times per second the worker publishes its results.
+    pub update_hz: f32,
+
+    /// Whether the worker is paused. A paused worker handles messages but doesn't search.
+    pub paused: bool,
+
+    /// The number of plies of the principal line to publish with the state data.
+    pub line_plies: usize,
+
+    /// Whether to publish the stats after each option with the state data.
+    pub publish_child_stats: bool,
+
     pub samples_per_second: f32,
+
+    /// The recent sample rates (oldest first), about one per second.
+    pub sps_history: VecDeque<f32>,
+
     pub average_search_depth: f32,
+    pub max_search_depth: u32,
+    pub new_nodes_per_second: f32,
+
+    /// The number of samples performed on each of the ponder states.
+    pub ponder_sample_counts: Vec<usize>,
+
+    /// The active state that the worker last accepted, if any.
+    pub active_state: Option<GameState>,
+
+    /// The last state queried with `Worker::stats_for`, and the cached stats for it.
+    pub queried_stats: Option<(GameState, Option<StateStats>)>,
+
+    /// A description of the error that stopped the worker thread, if it has crashed.
+    pub error: Option<String>,
 }
 
 /// Manages the worker thread performing game computations and facilitates
@@ -53,7 +147,7 @@ pub struct Worker {
     message_sender: Sender<Message>,
 
     /// The latest state data from the worker thread.
-    cur_state_data: Arc<Mutex<Option<WorkerStateData>>>,
+    cur_state_data: Arc<Mutex<Option<Arc<WorkerStateData>>>>,
 
     /// The shared overall data for the worker thread.
     cur_data: Arc<Mutex<WorkerData>>,
@@ -69,8 +163,24 @@ impl Worker {
         let cur_data = Arc::new(Mutex::new(WorkerData {
             cache_size: 0,
             cache_size_limit,
+            use_rave: false,
+            discount: 1.0,
+            exploration_rate: DEFAULT_EXPLORATION_RATE,
+            tablebase_stones: tablebase::DEFAULT_MAX_STONES,
+            rollout_budget: None,
+            update_hz: DEFAULT_UPDATE_HZ,
+            paused: false,
+            line_plies: 0,
+            publish_child_stats: false,
             samples_per_second: 0.0,
+            sps_history: VecDeque::new(),
             average_search_depth: 0.0,
+            max_search_depth: 0,
+            new_nodes_per_second: 0.0,
+            ponder_sample_counts: Vec::new(),
+            active_state: None,
+            queried_stats: None,
+            error: None,
         }));
         let cur_data2 = cur_data.clone();
 
@@ -81,75 +191,305 @@ impl Worker {
         let join_handle = thread::Builder::new()
             .name("worker".into())
             .spawn(move || {
-                println!("Worker thread started");
-                let update_delay = Duration::from_secs_f64(1.0 / 60.0); // delay between UI updates
-                let mut mcts_context = MCTSContext::new(cache_size_limit);
-                let mut active_game_state = None;
-
-                let send_update = |mcts_context: &MCTSContext, game_state: &GameState| {
-                    let new_state_data =
-                        mcts_context
-                            .stats_for(game_state)
-                            .map(|stats| WorkerStateData {
-                                game_state: game_state.clone(),
-                                stats: stats.clone(),
-                            });
-                    *cur_state_data2.lock() = new_state_data;
-                    cur_data2.lock().cache_size = mcts_context.cache_size();
-                    ui_context.request_repaint();
-                };
-
-                let mut last_sps_reading = Instant::now();
-                let mut num_samples = 0;
-                let mut sum_depths = 0;
-
-                'main_loop: loop {
-                    // handle any messages sent from the main thread
-                    for message in receiver.try_iter() {
-                        match message {
-                            Message::Stop => break 'main_loop,
-                            Message::ClearCache => mcts_context.clear_cache(),
-                            Message::SetActiveState(game_state) => {
-                                send_update(&mcts_context, &game_state);
-                                active_game_state = Some(game_state);
+                // catch any panic so that it can be reported to the main thread, rather
+                // than silently stopping the computation
+                let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                    println!("Worker thread started");
+                    let mut mcts_context = MCTSContext::new(cache_size_limit);
+                    let tablebase = Tablebase::new(tablebase::DEFAULT_MAX_STONES);
+                    mcts_context.tablebase = Some(Arc::new(tablebase));
+                    let mut active_game_state = None;
+                    let mut search_deadline: Option<Instant> = None;
+                    let mut ponder_states: Vec<GameState> = Vec::new();
+                    let mut next_target_index = 0;
+
+                    let mut score_history: Arc<Vec<VecDeque<f32>>> = Arc::default();
+                    let mut last_history_reading = Instant::now();
+
+                    // the time spent publishing updates rather than searching, which is
+                    // logged (at the debug level) with each sample rate reading
+                    let publish_time = Cell::new(Duration::ZERO);
+
+                    // the line and the child stats are only built if they're wanted, since
+                    // this runs for every update
+                    let send_update =
+                        |mcts_context: &MCTSContext,
+                         game_state: &GameState,
+                         score_history: &Arc<Vec<VecDeque<f32>>>| {
+                            let start_time = Instant::now();
+                            let (line_plies, publish_child_stats) = {
+                                let data = cur_data2.lock();
+                                (data.line_plies, data.publish_child_stats)
+                            };
+                            let new_state_data =
+                                mcts_context.stats_or_forced(game_state).map(|stats| {
+                                    let child_stats = if publish_child_stats {
+                                        game_state
+                                            .successors()
+                                            .map(|(_, next_state)| {
+                                                mcts_context.stats_or_forced(&next_state)
+                                            })
+                                            .collect()
+                                    } else {
+                                        Vec::new()
+                                    };
+                                    WorkerStateData {
+                                        game_state: game_state.clone(),
+                                        stats,
+                                        line: mcts_context.principal_line(game_state, line_plies),
+                                        score_history: score_history.clone(),
+                                        child_stats,
+                                    }
+                                });
+                            *cur_state_data2.lock() = new_state_data.map(Arc::new);
+                            cur_data2.lock().cache_size = mcts_context.cache_size();
+                            ui_context.request_repaint();
+                            publish_time.set(publish_time.get() + start_time.elapsed());
+                        };
+
+                    // when the state data was last published, and whether the search has
+                    // progressed since
+                    let mut last_update = Instant::now();
+                    let mut has_unpublished_progress = false;
+
+                    let mut last_sps_reading = Instant::now();
+                    let mut num_samples = 0;
+                    let mut sum_depths = 0;
+                    let mut max_depth = 0;
+                    let mut new_nodes = 0;
+
+                    'main_loop: loop {
+                        // handle any messages sent from the main thread
+                        for message in receiver.try_iter() {
+                            match message {
+                                Message::Stop => break 'main_loop,
+                                Message::ClearCache => {
+                                    mcts_context.clear_cache();
+
+                                    // start the readouts afresh too, rather than showing
+                                    // stats from before the clear until the next reading
+                                    last_sps_reading = Instant::now();
+                                    num_samples = 0;
+                                    sum_depths = 0;
+                                    max_depth = 0;
+                                    new_nodes = 0;
+                                    {
+                                        let mut data = cur_data2.lock();
+                                        data.cache_size = 0;
+                                        data.samples_per_second = 0.0;
+                                        data.sps_history.clear();
+                                        data.average_search_depth = 0.0;
+                                        data.max_search_depth = 0;
+                                        data.new_nodes_per_second = 0.0;
+                                    }
+                                    if let Some(active_game_state) = &active_game_state {
+                                        send_update(
+                                            &mcts_context,
+                                            active_game_state,
+                                            &score_history,
+                                        );
+                                    }
+                                    ui_context.request_repaint();
+                                }
+                                Message::SetActiveState(game_state) => {
+                                    score_history = Arc::default();
+                                    send_update(&mcts_context, &game_state, &score_history);
+                                    cur_data2.lock().active_state = Some(game_state.clone());
+                                    active_game_state = Some(game_state);
+                                    search_deadline = None;
+                                }
+                                Message::SearchFor(game_state, duration) => {
+                                    score_history = Arc::default();
+                                    send_update(&mcts_context, &game_state, &score_history);
+                                    cur_data2.lock().active_state = Some(game_state.clone());
+                                    active_game_state = Some(game_state);
+                                    search_deadline = Some(Instant::now() + duration);
+                                }
+                                Message::QueryStats(game_state) => {
+                                    let stats = mcts_context.stats_or_forced(&game_state);
+                                    let mut data = cur_data2.lock();
+                                    // only repaint for a new state, so that querying every
+                                    // frame doesn't keep the UI repainting
+                                    let is_new_state = data.queried_stats.as_ref().is_none_or(
+                                        |(queried_state, _)| *queried_state != game_state,
+                                    );
+                                    data.queried_stats = Some((game_state, stats));
+                                    if is_new_state {
+                                        ui_context.request_repaint();
+                                    }
+                                }
+                                Message::SetPonderStates(game_states) => {
+                                    cur_data2.lock().ponder_sample_counts =
+                                        vec![0; game_states.len()];
+                                    ponder_states = game_states;
+                                    next_target_index = 0;
+                                }
+                                Message::SetTablebaseStones(max_stones) => {
+                                    // the cached stats stay valid, since the tablebase only
+                                    // ever provides exact values
+                                    let tablebase = Tablebase::new(max_stones);
+                                    mcts_context.tablebase = Some(Arc::new(tablebase));
+                                    cur_data2.lock().tablebase_stones = max_stones;
+                                    ui_context.request_repaint();
+                                }
                             }
                         }
-                    }
 
-                    match &active_game_state {
-                        Some(game_state) if game_state.result().is_none() => {
-                            // do some MCTS computation
-                            mcts_context.cache_size_limit = cur_data2.lock().cache_size_limit;
-                            let (ponder_num_samples, ponder_sum_depths) =
-                                mcts_context.ponder(game_state, update_delay);
-                            num_samples += ponder_num_samples;
-                            sum_depths += ponder_sum_depths;
-
-                            // update the state data that the main thread can access
-                            send_update(&mcts_context, game_state);
+                        // pick the next non-terminal state to work on, cycling through the active
+                        // state (index 0, unless its rollout or time budget is used up) and then
+                        // each of the ponder states
+                        let (rollout_budget, update_delay, paused) = {
+                            let data = cur_data2.lock();
+                            let update_delay = Duration::from_secs_f32(1.0 / data.update_hz);
+                            (data.rollout_budget, update_delay, data.paused)
+                        };
+                        let within_budget = |game_state: &&GameState| {
+                            let within_rollouts = rollout_budget.is_none_or(|rollout_budget| {
+                                mcts_context
+                                    .stats_for(game_state)
+                                    .is_none_or(|stats| stats.num_rollouts < rollout_budget)
+                            });
+                            let within_time =
+                                search_deadline.is_none_or(|deadline| Instant::now() < deadline);
+                            within_rollouts && within_time
+                        };
+                        let num_targets = ponder_states.len() + 1;
+                        let target = (0..num_targets)
+                            .filter(|_| !paused)
+                            .map(|offset| (next_target_index + offset) % num_targets)
+                            .find_map(|target_index| {
+                                let game_state = match target_index {
+                                    0 => active_game_state.as_ref().filter(within_budget),
+                                    _ => ponder_states.get(target_index - 1),
+                                };
+                                game_state
+                                    .filter(|game_state| game_state.result().is_none())
+                                    .map(|game_state| (target_index, game_state))
+                            });
+
+                        match target {
+                            Some((target_index, game_state)) => {
+                                next_target_index = (target_index + 1) % num_targets;
+
+                                // do some MCTS computation
+                                {
+                                    let data = cur_data2.lock();
+                                    mcts_context.cache_size_limit = data.cache_size_limit;
+                                    mcts_context.use_rave = data.use_rave;
+                                    mcts_context.discount = data.discount;
+                                    mcts_context.exploration_rate = data.exploration_rate;
+                                }
+                                // don't search the active state past its deadline
+                                let ponder_time = match search_deadline {
+                                    Some(deadline) if target_index == 0 => WORK_SLICE
+                                        .min(deadline.saturating_duration_since(Instant::now())),
+                                    _ => WORK_SLICE,
+                                };
+                                let ponder_stats = mcts_context.ponder(game_state, ponder_time);
+                                num_samples += ponder_stats.num_samples;
+                                sum_depths += ponder_stats.sum_depths;
+                                max_depth = max_depth.max(ponder_stats.max_depth);
+                                new_nodes += ponder_stats.new_nodes;
+                                has_unpublished_progress = true;
+                                if target_index > 0 {
+                                    cur_data2.lock().ponder_sample_counts[target_index - 1] +=
+                                        ponder_stats.num_samples;
+                                }
+
+                                if let Some(active_game_state) = &active_game_state {
+                                    // record the options' expected scores every so often
+                                    let stats = mcts_context.stats_for(active_game_state);
+                                    if let Some(stats) = stats.filter(|_| {
+                                        last_history_reading.elapsed() >= SCORE_HISTORY_INTERVAL
+                                    }) {
+                                        last_history_reading = Instant::now();
+                                        // this copies the history if the last update's
+                                        // state data is still around
+                                        let score_history = Arc::make_mut(&mut score_history);
+                                        score_history
+                                            .resize_with(stats.options.len(), VecDeque::new);
+                                        for (history, option_stats) in
+                                            score_history.iter_mut().zip(&stats.options)
+                                        {
+                                            if history.len() == SCORE_HISTORY_LEN {
+                                                history.pop_front();
+                                            }
+                                            history.push_back(*option_stats.expected_score());
+                                        }
+                                    }
+
+                                    // update the state data that the main thread can access
+                                    if last_update.elapsed() >= update_delay {
+                                        send_update(
+                                            &mcts_context,
+                                            active_game_state,
+                                            &score_history,
+                                        );
+                                        last_update = Instant::now();
+                                        has_unpublished_progress = false;
+                                    }
+                                }
+                            }
+                            None => {
+                                // publish the final results of a search that just stopped
+                                if let Some(active_game_state) = active_game_state
+                                    .as_ref()
+                                    .filter(|_| has_unpublished_progress)
+                                {
+                                    send_update(&mcts_context, active_game_state, &score_history);
+                                    last_update = Instant::now();
+                                }
+                                has_unpublished_progress = false;
+                                thread::sleep(WORK_SLICE);
+                            }
                         }
-                        _ => thread::sleep(update_delay),
-                    }
 
-                    let elapsed = last_sps_reading.elapsed();
-                    if elapsed > Duration::from_secs_f32(1.0) {
-                        let new_sps = num_samples as f32 / elapsed.as_secs_f32();
-                        let new_asd = if num_samples == 0 {
-                            0.0
-                        } else {
-                            sum_depths as f32 / num_samples as f32
-                        };
-                        num_samples = 0;
-                        sum_depths = 0;
-                        last_sps_reading = Instant::now();
-
-                        let mut data = cur_data2.lock();
-                        if data.samples_per_second != new_sps {
-                            data.samples_per_second = new_sps;
-                            data.average_search_depth = new_asd;
-                            ui_context.request_repaint();
+                        let elapsed = last_sps_reading.elapsed();
+                        if elapsed > Duration::from_secs_f32(1.0) {
+                            let new_sps = num_samples as f32 / elapsed.as_secs_f32();
+                            let new_asd = if num_samples == 0 {
+                                0.0
+                            } else {
+                                sum_depths as f32 / num_samples as f32
+                            };
+                            let new_nps = new_nodes as f32 / elapsed.as_secs_f32();
+                            let new_max_depth = max_depth;
+                            log::debug!(
+                                "publishing updates took {:.2}% of the worker's time",
+                                publish_time.take().as_secs_f32() / elapsed.as_secs_f32() * 100.0,
+                            );
+                            num_samples = 0;
+                            sum_depths = 0;
+                            max_depth = 0;
+                            new_nodes = 0;
+                            last_sps_reading = Instant::now();
+
+                            let mut data = cur_data2.lock();
+                            if data.sps_history.len() == SPS_HISTORY_LEN {
+                                data.sps_history.pop_front();
+                            }
+                            data.sps_history.push_back(new_sps);
+                            if data.samples_per_second != new_sps {
+                                data.samples_per_second = new_sps;
+                                data.average_search_depth = new_asd;
+                                data.max_search_depth = new_max_depth;
+                                data.new_nodes_per_second = new_nps;
+                                ui_context.request_repaint();
+                            }
                         }
                     }
+                }));
+
+                if let Err(panic_payload) = result {
+                    let message = if let Some(message) = panic_payload.downcast_ref::<&str>() {
+                        message.to_string()
+                    } else if let Some(message) = panic_payload.downcast_ref::<String>() {
+                        message.clone()
+                    } else {
+                        "unknown error".to_string()
+                    };
+                    cur_data2.lock().error = Some(message);
+                    ui_context.request_repaint();
                 }
             })
             .expect("failed to spawn worker thread");
@@ -162,23 +502,54 @@ impl Worker {
         }
     }
 
+    /// Sends a message to the worker thread. If the worker thread has crashed, the
+    /// message is dropped (the error is reported by `error()`).
+    fn send(&self, message: Message) {
+        let _ = self.message_sender.send(message);
+    }
+
     /// Sets the active game state that the worker should compute on.
     pub fn set_active_state(&self, game_state: GameState) {
-        self.message_sender
-            .send(Message::SetActiveState(game_state))
-            .expect("failed to send to worker thread");
+        self.send(Message::SetActiveState(game_state));
+    }
+
+    /// Sets the active `GameState`, and has the worker stop searching it once the
+    /// given time has passed (e.g. for a bot's per-move think time). Like the rollout
+    /// budget, this only limits the active state; the ponder states are still searched.
+    pub fn search_for(&self, game_state: GameState, duration: Duration) {
+        self.send(Message::SearchFor(game_state, duration));
+    }
+
+    /// Sets additional game states that the worker should split its computation
+    /// with (in round-robin fashion), such as the positions after each of the
+    /// opponent's candidate moves. Pass an empty `Vec` to focus on the active state.
+    pub fn set_ponder_states(&self, game_states: Vec<GameState>) {
+        self.send(Message::SetPonderStates(game_states));
     }
 
     /// Clears the explored node cache.
     pub fn clear_cache(&self) {
-        self.message_sender
-            .send(Message::ClearCache)
-            .expect("failed to send to worker thread");
+        self.send(Message::ClearCache);
     }
 
-    /// Returns the current worker state data.
+    /// Returns the worker's cached stats for an arbitrary game state, if it has any,
+    /// without waiting for the worker thread: this asks the worker for fresh stats and
+    /// returns its answer to the previous query for the same state. So the first call
+    /// for a state returns None, and a repaint is requested once the answer arrives
+    /// (usually within one UI update interval); call this every frame to keep it fresh.
     #[must_use]
-    pub fn state_data(&self) -> Option<WorkerStateData> {
+    pub fn stats_for(&self, game_state: &GameState) -> Option<StateStats> {
+        self.send(Message::QueryStats(game_state.clone()));
+        match &self.cur_data.lock().queried_stats {
+            Some((queried_state, stats)) if queried_state == game_state => stats.clone(),
+            _ => None,
+        }
+    }
+
+    /// Returns the current worker state data. It's shared rather than copied, so
+    /// this is cheap to call every frame.
+    #[must_use]
+    pub fn state_data(&self) -> Option<Arc<WorkerStateData>> {
         self.cur_state_data.lock().clone()
     }
 
@@ -199,26 +570,164 @@ impl Worker {
         self.cur_data.lock().cache_size_limit = cache_size_limit;
     }
 
+    /// Returns whether the worker blends AMAF (RAVE) stats into move selection.
+    #[must_use]
+    pub fn use_rave(&self) -> bool {
+        self.cur_data.lock().use_rave
+    }
+
+    /// Sets whether the worker blends AMAF (RAVE) stats into move selection.
+    pub fn set_use_rave(&self, use_rave: bool) {
+        self.cur_data.lock().use_rave = use_rave;
+    }
+
+    /// Returns the per-ply discount the worker applies to rollout scores.
+    #[must_use]
+    pub fn discount(&self) -> f32 {
+        self.cur_data.lock().discount
+    }
+
+    /// Sets the per-ply discount the worker applies to rollout scores (1 for none).
+    pub fn set_discount(&self, discount: f32) {
+        self.cur_data.lock().discount = discount;
+    }
+
+    /// Returns the worker's exploration constant.
+    #[must_use]
+    pub fn exploration_rate(&self) -> f32 {
+        self.cur_data.lock().exploration_rate
+    }
+
+    /// Sets the worker's exploration constant. Existing stats are kept; only later
+    /// selections are affected.
+    pub fn set_exploration_rate(&self, exploration_rate: f32) {
+        self.cur_data.lock().exploration_rate = exploration_rate;
+    }
+
+    /// Returns the maximum number of stones in holes covered by the worker's tablebase.
+    /// This lags behind `set_tablebase_stones` until the new tablebase is built.
+    #[must_use]
+    pub fn tablebase_stones(&self) -> u8 {
+        self.cur_data.lock().tablebase_stones
+    }
+
+    /// Has the worker rebuild its tablebase to cover positions with up to `max_stones`
+    /// stones in holes. This can take a while for larger values, during which the
+    /// worker doesn't search.
+    pub fn set_tablebase_stones(&self, max_stones: u8) {
+        self.send(Message::SetTablebaseStones(max_stones));
+    }
+
+    /// Returns the number of samples performed on each of the ponder states
+    /// since they were set.
+    #[must_use]
+    pub fn ponder_sample_counts(&self) -> Vec<usize> {
+        self.cur_data.lock().ponder_sample_counts.clone()
+    }
+
+    /// Returns the active state that the worker last accepted, which lags behind
+    /// `set_active_state` until the worker processes the message.
+    #[must_use]
+    pub fn active_state(&self) -> Option<GameState> {
+        self.cur_data.lock().active_state.clone()
+    }
+
+    /// Returns the rollout budget for the active state, if any.
+    #[must_use]
+    pub fn rollout_budget(&self) -> Option<u32> {
+        self.cur_data.lock().rollout_budget
+    }
+
+    /// Sets the number of rollouts at the active state after which the worker stops
+    /// searching it (or None to search indefinitely).
+    pub fn set_rollout_budget(&self, rollout_budget: Option<u32>) {
+        self.cur_data.lock().rollout_budget = rollout_budget;
+    }
+
+    /// Returns how many times per second the worker publishes its results.
+    #[must_use]
+    pub fn update_hz(&self) -> f32 {
+        self.cur_data.lock().update_hz
+    }
+
+    /// Sets how many times per second the worker publishes its results. Lower rates
+    /// leave more time for searching.
+    pub fn set_update_hz(&self, update_hz: f32) {
+        assert!(update_hz > 0.0, "update rate must be positive");
+        self.cur_data.lock().update_hz = update_hz;
+    }
+
+    /// Returns whether the worker is paused.
+    #[must_use]
+    pub fn is_paused(&self) -> bool {
+        self.cur_data.lock().paused
+    }
+
+    /// Pauses or resumes the worker's search. The search picks up where it left off.
+    pub fn set_paused(&self, paused: bool) {
+        self.cur_data.lock().paused = paused;
+    }
+
+    /// Sets whether the stats after each option are published with the state data
+    /// (`WorkerStateData::child_stats`). They're only built while this is set.
+    pub fn set_publish_child_stats(&self, publish_child_stats: bool) {
+        self.cur_data.lock().publish_child_stats = publish_child_stats;
+    }
+
+    /// Returns the number of plies of the principal line published with the state data.
+    #[must_use]
+    pub fn line_plies(&self) -> usize {
+        self.cur_data.lock().line_plies
+    }
+
+    /// Sets the number of plies of the principal line to publish with the state data
+    /// (0 to skip computing it).
+    pub fn set_line_plies(&self, line_plies: usize) {
+        self.cur_data.lock().line_plies = line_plies;
+    }
+
+    /// Returns a description of the error that stopped the worker thread, if it has crashed.
+    #[must_use]
+    pub fn error(&self) -> Option<String> {
+        self.cur_data.lock().error.clone()
+    }
+
     /// Returns the worker's current sample rate.
     #[must_use]
     pub fn samples_per_second(&self) -> f32 {
         self.cur_data.lock().samples_per_second
     }
 
+    /// Returns the worker's recent sample rates (oldest first), about one per second.
+    #[must_use]
+    pub fn sps_history(&self) -> VecDeque<f32> {
+        self.cur_data.lock().sps_history.clone()
+    }
+
     /// Returns the worker's current average search depth.
     #[must_use]
     pub fn average_search_depth(&self) -> f32 {
         self.cur_data.lock().average_search_depth
     }
+
+    /// Returns the worker's recent maximum search depth.
+    #[must_use]
+    pub fn max_search_depth(&self) -> u32 {
+        self.cur_data.lock().max_search_depth
+    }
+
+    /// Returns the worker's current rate of adding nodes to the cache.
+    #[must_use]
+    pub fn new_nodes_per_second(&self) -> f32 {
+        self.cur_data.lock().new_nodes_per_second
+    }
 }
 
 impl Drop for Worker {
     fn drop(&mut self) {
         let _ = self.message_sender.send(Message::Stop);
-        self.join_handle
-            .take()
-            .unwrap()
-            .join()
-            .expect("worker thread should not panic");
+
+        // any panic in the worker thread has already been caught and reported
+        let _ = self.join_handle.take().unwrap().join();
     }
 }